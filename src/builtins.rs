@@ -0,0 +1,101 @@
+use crate::checker::Type;
+use crate::runtime::{InterpreterRuntime, RuntimeError};
+use crate::scanner::Token;
+use crate::value::{MapKey, Value};
+
+/// Function which is available without declaration. A user defined function
+/// with the same name shadows it.
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: usize,
+    pub return_type: Type,
+    /// Receives the runtime to call function arguments with, the token of the call and the already evaluated arguments
+    pub function: fn(&mut InterpreterRuntime, &Token, &[Value]) -> Result<Value, RuntimeError>,
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin { name: "len", arity: 1, return_type: Type::Int, function: len },
+    Builtin { name: "keys", arity: 1, return_type: Type::List, function: keys },
+    Builtin { name: "values", arity: 1, return_type: Type::List, function: values },
+    Builtin { name: "items", arity: 1, return_type: Type::List, function: items },
+    Builtin { name: "map", arity: 2, return_type: Type::List, function: map },
+    Builtin { name: "filter", arity: 2, return_type: Type::List, function: filter },
+    Builtin { name: "reduce", arity: 3, return_type: Type::Unknown, function: reduce },
+];
+
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// Number of elements of a list, characters of a string or entries of a map
+fn len(_runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let length = match &arguments[0] {
+        Value::List(elements) => elements.borrow().len(),
+        Value::Str(value) => value.chars().count(),
+        Value::Map(entries) => entries.borrow().len(),
+        other => return Err(RuntimeError::at(token, format!("`len` expects a list, string or map, found {}", other.type_name())))
+    };
+
+    Ok(Value::Int(length as i64))
+}
+
+fn keys(_runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    map_entries(token, "keys", &arguments[0], |key, _| key.to_value())
+}
+
+fn values(_runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    map_entries(token, "values", &arguments[0], |_, value| value.clone())
+}
+
+/// Entries of a map as `[key, value]` lists
+fn items(_runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    map_entries(token, "items", &arguments[0], |key, value| Value::list(vec![key.to_value(), value.clone()]))
+}
+
+/// `map(list, function)` calls the function with every element and collects the results
+fn map(runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let mut results: Vec<Value> = vec![];
+    for element in list_elements(token, "map", &arguments[0])? {
+        results.push(runtime.call_value(&arguments[1], token, vec![element])?);
+    }
+
+    Ok(Value::list(results))
+}
+
+/// `filter(list, function)` keeps the elements for which the function returns a truthy value
+fn filter(runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let mut results: Vec<Value> = vec![];
+    for element in list_elements(token, "filter", &arguments[0])? {
+        if runtime.call_value(&arguments[1], token, vec![element.clone()])?.is_truthy() {
+            results.push(element);
+        }
+    }
+
+    Ok(Value::list(results))
+}
+
+/// `reduce(list, function, initial)` folds the list from the left, calling `function(accumulator, element)`
+fn reduce(runtime: &mut InterpreterRuntime, token: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let mut accumulator = arguments[2].clone();
+    for element in list_elements(token, "reduce", &arguments[0])? {
+        accumulator = runtime.call_value(&arguments[1], token, vec![accumulator, element])?;
+    }
+
+    Ok(accumulator)
+}
+
+/// Copy of the elements, so the called function may change the list while it is iterated
+fn list_elements(token: &Token, name: &str, list: &Value) -> Result<Vec<Value>, RuntimeError> {
+    match list {
+        Value::List(elements) => Ok(elements.borrow().clone()),
+        other => Err(RuntimeError::at(token, format!("`{}` expects a list, found {}", name, other.type_name())))
+    }
+}
+
+/// Collects one value per map entry into a list, in insertion order
+fn map_entries(token: &Token, name: &str, map: &Value, entry: fn(&MapKey, &Value) -> Value) -> Result<Value, RuntimeError> {
+    match map {
+        Value::Map(entries) => Ok(Value::list(entries.borrow().iter().map(|(key, value)| entry(key, value)).collect())),
+        other => Err(RuntimeError::at(token, format!("`{}` expects a map, found {}", name, other.type_name())))
+    }
+}
@@ -0,0 +1,1240 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::rc::Rc;
+use crate::expressions::{ApplyExpr, AssignExpr, BinaryExpr, BoolExpr, BreakExpr, CallExpr, ConstExpr, ContinueExpr, EmptyExpr, EnumExpr, Expression, ExpressionKind, ExpressionVisitor, FloatExpr, ForExpr, FunctionExpr, GetExpr, IfExpr, ImplExpr, ImportExpr, IndexExpr, IntExpr, LambdaExpr, ListExpr, MapExpr, MatchExpr, MethodCallExpr, Pattern, PrintExpr, ReturnExpr, SliceExpr, StringExpr, StructExpr, TypeExpr, UnaryExpr, VarExpr, VariableExpr, WhileExpr};
+use crate::builtins;
+use crate::codes::Code;
+use crate::modules::{self, Module, Program};
+use crate::parser::Ast;
+use crate::reporter::{CodeReporter, Diagnostic};
+use crate::scanner::{Span, Token, TokenType};
+
+/// Static type of an expression.
+///
+/// Everything which is not annotated and cannot be inferred is `Unknown`,
+/// which is compatible with every other type and only checked at runtime.
+#[derive(Clone, PartialEq)]
+pub enum Type {
+    Unknown,
+    Empty,
+    Int,
+    Float,
+    String,
+    Bool,
+    List,
+    Map,
+    Function,
+    /// Instance of the user defined struct with this name
+    Struct(String),
+    /// Variant of the user defined enum with this name
+    Enum(String),
+    /// Module bound by `import`, identified by its key
+    Module(String),
+}
+
+impl Type {
+    fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "string" => Some(Type::String),
+            "bool" => Some(Type::Bool),
+            "list" => Some(Type::List),
+            "map" => Some(Type::Map),
+            "function" => Some(Type::Function),
+            _ => None
+        }
+    }
+
+    /// Whether a value of type `found` can be used where `self` is expected.
+    /// Ints are only promoted inside arithmetic, a `float` binding needs a float value.
+    fn accepts(&self, found: &Type) -> bool {
+        *self == Type::Unknown || *found == Type::Unknown || self == found
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    fn is_sequence(&self) -> bool {
+        matches!(self, Type::Unknown | Type::List | Type::String)
+    }
+
+    fn is_map_key(&self) -> bool {
+        matches!(self, Type::Unknown | Type::String | Type::Int | Type::Bool)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Type::Unknown => "unknown",
+            Type::Empty => "empty",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::String => "string",
+            Type::Bool => "bool",
+            Type::List => "list",
+            Type::Map => "map",
+            Type::Function => "function",
+            Type::Struct(name) | Type::Enum(name) => name,
+            Type::Module(..) => "module",
+        })
+    }
+}
+
+/// Fields of a struct in declaration order
+struct StructSignature {
+    fields: Vec<(String, Type)>,
+}
+
+/// Variants of an enum in declaration order with the types of their fields
+struct EnumSignature {
+    variants: Vec<(String, Vec<Type>)>,
+}
+
+/// Parameter types of a method include its `self` parameter
+#[derive(Clone)]
+struct FunctionSignature {
+    parameters: Vec<Type>,
+    return_type: Type,
+    is_method: bool,
+}
+
+/// Top level functions and constants of a module. Types are not exported,
+/// so values of the types of a module are only checked at runtime in other modules.
+struct ModuleSignature {
+    name: String,
+    functions: HashMap<String, FunctionSignature>,
+    constants: HashMap<String, Type>,
+}
+
+pub struct TypeChecker {
+    reporter: CodeReporter,
+    /// File of the module being checked, expressions only know their span
+    file_name: String,
+    /// Modules checked so far by their key
+    modules: HashMap<String, ModuleSignature>,
+    /// Functions by owning type, free functions are stored under `None`
+    functions: HashMap<Option<String>, HashMap<String, FunctionSignature>>,
+    structs: HashMap<String, StructSignature>,
+    enums: HashMap<String, EnumSignature>,
+    scopes: Vec<HashMap<String, Type>>,
+    return_types: Vec<Type>,
+}
+
+impl TypeChecker {
+    pub fn new(reporter: CodeReporter) -> Self {
+        TypeChecker {
+            reporter,
+            file_name: String::new(),
+            modules: HashMap::new(),
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            return_types: vec![],
+        }
+    }
+
+    /// Checks every module of the program, each after the modules it imports
+    pub fn check_program(&mut self, program: &Program) -> bool {
+        for module in &program.modules {
+            self.functions = HashMap::new();
+            self.structs = HashMap::new();
+            self.enums = HashMap::new();
+            self.scopes = vec![HashMap::new()];
+            self.file_name = module.file_name.to_string();
+
+            self.check_ast(&module.ast);
+            self.export_module(module);
+        }
+
+        !self.reporter.has_error()
+    }
+
+    fn export_module(&mut self, module: &Module) {
+        let mut functions = HashMap::new();
+        let mut constants = HashMap::new();
+
+        for expression in &module.ast.expressions {
+            match expression.kind() {
+                ExpressionKind::Func(function) => {
+                    if let Some(signature) = self.function(None, &function.name) {
+                        let parameters = signature.parameters.into_iter().map(exported_type).collect();
+                        let return_type = exported_type(signature.return_type);
+                        functions.insert(function.name.to_string(), FunctionSignature { parameters, return_type, is_method: false });
+                    }
+                },
+                ExpressionKind::Const(constant) => {
+                    let constant_type = self.binding(&constant.variable).unwrap_or(Type::Unknown);
+                    constants.insert(constant.variable.to_string(), exported_type(constant_type));
+                },
+                _ => {}
+            }
+        }
+
+        let name = Path::new(&module.file_name).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        self.modules.insert(module.key.to_string(), ModuleSignature { name, functions, constants });
+    }
+
+    /// Checks a single module and returns whether it is free of type errors
+    pub fn check_ast(&mut self, ast: &Ast) -> bool {
+        // top level types and functions can be used before their declaration,
+        // types come first as functions may use them in annotations
+        self.declare_types(ast);
+
+        for expression in &ast.expressions {
+            if let ExpressionKind::Func(function) = expression.kind() {
+                self.declare_function(function);
+            } else if let ExpressionKind::Impl(implementation) = expression.kind() {
+                for function in &implementation.functions {
+                    self.declare_function(function);
+                }
+            }
+        }
+
+        self.check_body(&ast.expressions);
+
+        !self.reporter.has_error()
+    }
+
+    fn check_body(&mut self, body: &[Box<dyn Expression>]) {
+        for body_expr in body {
+            body_expr.accept(self);
+        }
+    }
+
+    /// Mirrors the runtime, which runs branches and loop bodies in their own scope
+    fn check_block(&mut self, body: &[Box<dyn Expression>]) {
+        self.scopes.push(HashMap::new());
+        self.check_body(body);
+        self.scopes.pop();
+    }
+
+    fn declare_function(&mut self, function: &FunctionExpr) {
+        let mut parameters: Vec<Type> = function.parameters.iter()
+            .map(|parameter| self.annotation_type(parameter.type_annotation.as_ref()))
+            .collect();
+        if let Some(self_type) = self.self_type(function) {
+            parameters[0] = self_type;
+        }
+
+        let return_type = self.annotation_type(function.return_type.as_ref());
+        let signature = FunctionSignature { parameters, return_type, is_method: function.is_method() };
+
+        if self.function(function.owner.as_deref(), &function.name).is_some() {
+            self.report(function.token.span, Code::DuplicateDeclaration, format!("Function `{}` is declared twice", function.qualified_name()));
+            return;
+        }
+
+        self.functions.entry(function.owner.clone()).or_default().insert(function.name.to_string(), signature);
+    }
+
+    /// The `self` parameter of a method has the type the method is implemented for
+    fn self_type(&self, function: &FunctionExpr) -> Option<Type> {
+        if !function.is_method() {
+            return None;
+        }
+
+        function.owner.as_deref().and_then(|owner| self.resolve_type(owner))
+    }
+
+    /// Checks the body of a declared function or lambda, which like at runtime
+    /// sees the scopes around its declaration and its parameters
+    fn check_function_body(&mut self, function: &FunctionExpr) {
+        let mut scope = HashMap::new();
+        for parameter in &function.parameters {
+            let parameter_type = self.check_annotation(parameter.type_annotation.as_ref());
+            scope.insert(parameter.name.to_string(), parameter_type);
+        }
+
+        if let Some(self_type) = self.self_type(function) {
+            scope.insert(String::from("self"), self_type);
+        }
+
+        let return_type = self.check_annotation(function.return_type.as_ref());
+
+        self.scopes.push(scope);
+        self.return_types.push(return_type);
+        self.check_body(&function.body);
+        self.return_types.pop();
+        self.scopes.pop();
+    }
+
+    fn function(&self, owner: Option<&str>, name: &str) -> Option<FunctionSignature> {
+        self.functions.get(&owner.map(String::from))?.get(name).cloned()
+    }
+
+    /// Declares the top level structs and enums, their names before their fields
+    /// so fields can have the type of any of them
+    fn declare_types(&mut self, ast: &Ast) {
+        let mut definitions = vec![];
+        for expression in &ast.expressions {
+            let (name, span) = match expression.kind() {
+                ExpressionKind::Struct(definition) => (&definition.name, definition.token.span),
+                ExpressionKind::Enum(definition) => (&definition.name, definition.token.span),
+                _ => continue
+            };
+
+            if self.structs.contains_key(name) || self.enums.contains_key(name) {
+                self.report(span, Code::DuplicateDeclaration, format!("Type `{}` is declared twice", name));
+                continue;
+            }
+
+            if let ExpressionKind::Struct(..) = expression.kind() {
+                self.structs.insert(name.to_string(), StructSignature { fields: vec![] });
+            } else {
+                self.enums.insert(name.to_string(), EnumSignature { variants: vec![] });
+            }
+
+            definitions.push(expression.kind());
+        }
+
+        for definition in definitions {
+            match definition {
+                ExpressionKind::Struct(definition) => self.declare_struct(definition),
+                ExpressionKind::Enum(definition) => self.declare_enum(definition),
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_struct(&mut self, definition: &StructExpr) {
+        let fields = definition.fields.iter()
+            .map(|field| (field.name.to_string(), self.annotation_type(field.type_annotation.as_ref())))
+            .collect();
+
+        self.structs.insert(definition.name.to_string(), StructSignature { fields });
+    }
+
+    fn declare_enum(&mut self, definition: &EnumExpr) {
+        let variants = definition.variants.iter()
+            .map(|variant| {
+                let fields = variant.fields.iter()
+                    .map(|field| self.annotation_type(field.type_annotation.as_ref()))
+                    .collect();
+                (variant.name.to_string(), fields)
+            })
+            .collect();
+
+        self.enums.insert(definition.name.to_string(), EnumSignature { variants });
+    }
+
+    /// Built-in type, struct or enum with this name
+    fn resolve_type(&self, name: &str) -> Option<Type> {
+        if self.structs.contains_key(name) {
+            return Some(Type::Struct(name.to_string()));
+        }
+
+        if self.enums.contains_key(name) {
+            return Some(Type::Enum(name.to_string()));
+        }
+
+        Type::from_name(name)
+    }
+
+    /// Type named by the object of `Shape.Circle` or `Point.origin()`, unless a variable shadows the type name
+    fn type_of(&self, object: &dyn Expression) -> Option<String> {
+        match object.kind() {
+            ExpressionKind::Variable(variable) if (self.structs.contains_key(&variable.name) || self.enums.contains_key(&variable.name))
+                && !self.scopes.iter().any(|scope| scope.contains_key(&variable.name)) => Some(variable.name.to_string()),
+            _ => None
+        }
+    }
+
+    /// Enum named by the object of `Shape.Circle`
+    fn enum_of(&self, object: &dyn Expression) -> Option<String> {
+        self.type_of(object).filter(|name| self.enums.contains_key(name))
+    }
+
+    /// Checks the arguments of a call against the parameter types, `name` is used in the messages
+    /// Mismatched arguments are reported at the argument, a wrong count at the whole call
+    fn check_arguments(&mut self, span: Span, name: &str, parameters: &[Type], arguments: &[Box<dyn Expression>], argument_types: &[Type]) {
+        if parameters.len() != argument_types.len() {
+            return self.report(span, Code::ArgumentCount, format!(
+                "Function `{}` expects {} arguments but got {}", name, parameters.len(), argument_types.len()
+            ));
+        }
+
+        for (index, (expected, found)) in parameters.iter().zip(argument_types).enumerate() {
+            if !expected.accepts(found) {
+                self.report(arguments[index].span(), Code::MismatchedTypes, format!(
+                    "Mismatched types for argument {} of `{}`: expected {}, found {}", index + 1, name, expected, found
+                ));
+            }
+        }
+    }
+
+    /// `Type.name(...)` constructs an enum variant or calls a function of the type which is no method
+    fn check_type_call(&mut self, type_name: &str, expr: &MethodCallExpr, argument_types: &[Type]) -> Type {
+        let is_variant = self.enums.get(type_name)
+            .is_some_and(|signature| signature.variants.iter().any(|(variant, _)| *variant == expr.name));
+        if is_variant {
+            return self.check_variant_construction(type_name, &expr.token, expr.span, &expr.arguments, argument_types);
+        }
+
+        let qualified_name = format!("{}.{}", type_name, expr.name);
+        let (parameters, return_type) = match self.function(Some(type_name), &expr.name) {
+            Some(signature) if signature.is_method => {
+                self.report(expr.token.span, Code::InvalidCall, format!("`{}` is a method and has to be called on an instance", qualified_name));
+                return signature.return_type;
+            },
+            Some(signature) => (signature.parameters, signature.return_type),
+            None if self.enums.contains_key(type_name) => return self.check_variant_construction(type_name, &expr.token, expr.span, &expr.arguments, argument_types),
+            None => {
+                self.report(expr.token.span, Code::UnknownName, format!("Type `{}` has no function `{}`", type_name, expr.name));
+                return Type::Unknown;
+            }
+        };
+
+        self.check_arguments(expr.span, &qualified_name, &parameters, &expr.arguments, argument_types);
+        return_type
+    }
+
+    /// Field types of a variant, reports unknown variants
+    fn variant_fields(&mut self, enum_name: &str, variant: &Token) -> Option<Vec<Type>> {
+        let fields = self.enums.get(enum_name)
+            .and_then(|signature| signature.variants.iter().find(|(name, _)| *name == variant.lexeme))
+            .map(|(_, fields)| fields.clone());
+
+        if fields.is_none() {
+            self.report(variant.span, Code::UnknownName, format!("Enum `{}` has no variant `{}`", enum_name, variant.lexeme));
+        }
+
+        fields
+    }
+
+    /// `span` covers the whole construction, which is reported if the number of values is wrong
+    fn check_variant_construction(
+        &mut self,
+        enum_name: &str,
+        variant: &Token,
+        span: Span,
+        arguments: &[Box<dyn Expression>],
+        argument_types: &[Type]
+    ) -> Type {
+        let fields = match self.variant_fields(enum_name, variant) {
+            Some(fields) => fields,
+            None => return Type::Unknown
+        };
+
+        if fields.len() != argument_types.len() {
+            self.report(span, Code::ArgumentCount, format!(
+                "Variant `{}.{}` expects {} values but got {}", enum_name, variant.lexeme, fields.len(), argument_types.len()
+            ));
+        } else {
+            for (index, (expected, found)) in fields.iter().zip(argument_types).enumerate() {
+                if !expected.accepts(found) {
+                    self.report(arguments[index].span(), Code::MismatchedTypes, format!(
+                        "Mismatched types for value {} of `{}.{}`: expected {}, found {}",
+                        index + 1, enum_name, variant.lexeme, expected, found
+                    ));
+                }
+            }
+        }
+
+        Type::Enum(enum_name.to_string())
+    }
+
+    /// Checks a pattern against the type of the value it is matched with and binds its names
+    fn check_pattern(&mut self, pattern: &Pattern, expected: &Type) {
+        match pattern {
+            Pattern::Wildcard => {},
+            Pattern::Binding(name) => {
+                self.scopes.last_mut().unwrap().insert(name.lexeme.to_string(), expected.clone());
+            },
+            Pattern::Literal(literal) => {
+                let found = literal.accept(self);
+                let comparable = expected.accepts(&found) || (expected.is_numeric() && found.is_numeric());
+
+                if !comparable {
+                    self.report(literal.span(), Code::MismatchedTypes, format!("Mismatched types in pattern: expected {}, found {}", expected, found));
+                }
+            },
+            Pattern::Variant { enum_name, variant, fields } => {
+                if !self.enums.contains_key(&enum_name.lexeme) {
+                    self.report(enum_name.span, Code::UnknownName, format!("Unknown enum `{}`", enum_name.lexeme));
+                    return;
+                }
+
+                let found = Type::Enum(enum_name.lexeme.to_string());
+                if !expected.accepts(&found) {
+                    self.report(enum_name.span, Code::MismatchedTypes, format!("Mismatched types in pattern: expected {}, found {}", expected, found));
+                }
+
+                let field_types = match self.variant_fields(&enum_name.lexeme, variant) {
+                    Some(field_types) => field_types,
+                    None => return
+                };
+
+                if field_types.len() != fields.len() {
+                    self.report(variant.span, Code::ArgumentCount, format!(
+                        "Variant `{}.{}` has {} values but the pattern has {}",
+                        enum_name.lexeme, variant.lexeme, field_types.len(), fields.len()
+                    ));
+                    return;
+                }
+
+                for (field, field_type) in fields.iter().zip(&field_types) {
+                    self.check_pattern(field, field_type);
+                }
+            }
+        }
+    }
+
+    /// Warns about values no arm of a match can handle. Only arms without guard count,
+    /// a variant is covered by an arm whose field patterns all match anything.
+    fn check_exhaustive(&mut self, expr: &MatchExpr, subject_type: &Type) {
+        let patterns: Vec<&Pattern> = expr.arms.iter()
+            .filter(|arm| matches!(arm.guard.kind(), ExpressionKind::Empty(..)))
+            .map(|arm| &arm.pattern)
+            .collect();
+
+        if patterns.iter().any(|pattern| pattern.is_irrefutable()) {
+            return;
+        }
+
+        // without a known type the enum is taken from the variant patterns
+        let enum_name = match subject_type {
+            Type::Enum(name) => Some(name.to_string()),
+            Type::Unknown => expr.arms.iter().find_map(|arm| match &arm.pattern {
+                Pattern::Variant { enum_name, .. } => Some(enum_name.lexeme.to_string()),
+                _ => None
+            }),
+            _ => None
+        };
+
+        let missing: Vec<String> = match (&enum_name, subject_type) {
+            (Some(enum_name), _) => match self.enums.get(enum_name) {
+                Some(signature) => signature.variants.iter()
+                    .filter(|(variant_name, _)| !patterns.iter().any(|pattern| matches!(
+                        pattern,
+                        Pattern::Variant { variant, fields, .. }
+                            if variant.lexeme == *variant_name && fields.iter().all(Pattern::is_irrefutable)
+                    )))
+                    .map(|(variant_name, _)| format!("`{}.{}`", enum_name, variant_name))
+                    .collect(),
+                None => return
+            },
+            (None, Type::Bool) => [true, false].iter()
+                .filter(|value| !patterns.iter().any(|pattern| matches!(
+                    pattern,
+                    Pattern::Literal(literal) if matches!(literal.kind(), ExpressionKind::Bool(bool_expr) if bool_expr.value == **value)
+                )))
+                .map(|value| format!("`{}`", value))
+                .collect(),
+            _ => vec![String::from("a `_` arm")]
+        };
+
+        if !missing.is_empty() {
+            let message = format!("Non-exhaustive match, missing {}", missing.join(", "));
+            self.reporter.report(Diagnostic::warning(Code::NonExhaustiveMatch, &self.file_name, expr.span(), &message)
+                .with_help("add an arm for every missing case or a `_` arm for the rest"));
+        }
+    }
+
+    /// Type named by an annotation, `Unknown` if there is none
+    fn annotation_type(&self, annotation: &dyn Expression) -> Type {
+        match annotation.kind() {
+            ExpressionKind::Type(type_expr) => self.resolve_type(&type_expr.name).unwrap_or(Type::Unknown),
+            _ => Type::Unknown
+        }
+    }
+
+    /// Checks the named arguments of a struct construction against its fields
+    fn check_construction(&mut self, expr: &CallExpr, argument_count: usize) -> Type {
+        let mut errors: Vec<(Span, Code, String)> = vec![];
+        let argument_types: Vec<(Token, Type)> = expr.named_arguments.iter()
+            .map(|(name, value)| (name.clone(), value.accept(self)))
+            .collect();
+
+        if argument_count > 0 {
+            self.report(expr.span, Code::InvalidCall, format!("Struct `{}` has to be constructed with named fields", expr.name));
+            return Type::Struct(expr.name.to_string());
+        }
+
+        let signature = &self.structs[&expr.name];
+
+        for ((name, found), (_, value)) in argument_types.iter().zip(&expr.named_arguments) {
+            match signature.fields.iter().find(|(field, _)| *field == name.lexeme) {
+                Some((_, expected)) if !expected.accepts(found) => errors.push((value.span(), Code::MismatchedTypes, format!(
+                    "Mismatched types for field `{}` of `{}`: expected {}, found {}", name.lexeme, expr.name, expected, found
+                ))),
+                Some(..) => {},
+                None => errors.push((name.span, Code::UnknownName, format!("Struct `{}` has no field `{}`", expr.name, name.lexeme))),
+            }
+        }
+
+        for (field, _) in &signature.fields {
+            if !argument_types.iter().any(|(name, _)| name.lexeme == *field) {
+                errors.push((expr.span, Code::ArgumentCount, format!("Missing field `{}` for struct `{}`", field, expr.name)));
+            }
+        }
+
+        for (span, code, error) in errors {
+            self.report(span, code, error);
+        }
+
+        Type::Struct(expr.name.to_string())
+    }
+
+    /// Like `annotation_type` but reports unknown type names
+    fn check_annotation(&mut self, annotation: &dyn Expression) -> Type {
+        match annotation.kind() {
+            ExpressionKind::Type(..) => annotation.accept(self),
+            _ => Type::Unknown
+        }
+    }
+
+    /// Checks the value of a `const` or `var` against its annotation and returns the
+    /// annotated type, or the type of the value if there is no annotation
+    fn check_declaration(&mut self, variable: &str, annotation: &dyn Expression, value: &dyn Expression) -> Type {
+        let value_type = value.accept(self);
+        let annotated_type = self.check_annotation(annotation);
+
+        if !annotated_type.accepts(&value_type) {
+            let message = format!("Mismatched types for `{}`: expected {}, found {}", variable, annotated_type, value_type);
+            self.reporter.report(Diagnostic::error(Code::MismatchedTypes, &self.file_name, value.span(), &message)
+                .with_label(annotation.span(), "expected due to this annotation"));
+        }
+
+        if annotated_type == Type::Unknown { value_type } else { annotated_type }
+    }
+
+    fn check_builtin_call(&mut self, expr: &CallExpr, argument_count: usize) -> Type {
+        let builtin = match builtins::find(&expr.name) {
+            Some(builtin) => builtin,
+            None => return Type::Unknown
+        };
+
+        if builtin.arity != argument_count {
+            self.report(expr.token.span, Code::ArgumentCount, format!(
+                "Function `{}` expects {} arguments but got {}", builtin.name, builtin.arity, argument_count
+            ));
+        }
+
+        builtin.return_type.clone()
+    }
+
+    /// Checks the object of an index or slice and returns its type
+    fn check_sequence(&mut self, object: &dyn Expression, token: &Token) -> Type {
+        let object_type = object.accept(self);
+        if !object_type.is_sequence() {
+            self.report(token.span, Code::UnsupportedOperation, format!("Cannot index into {}", object_type));
+            return Type::Unknown;
+        }
+
+        object_type
+    }
+
+    fn check_index_type(&mut self, index: &dyn Expression, token: &Token) {
+        let index_type = index.accept(self);
+        if !matches!(index_type, Type::Unknown | Type::Int | Type::Empty) {
+            self.report(token.span, Code::MismatchedTypes, format!("Index has to be an int, found {}", index_type));
+        }
+    }
+
+    fn report(&mut self, span: Span, code: Code, message: String) {
+        self.reporter.report_error(code, &self.file_name, span, &message);
+    }
+
+    /// Type of the field `name` of a struct type, `None` for other types or unknown fields
+    fn field_type(&self, object_type: &Type, name: &str) -> Option<Type> {
+        let struct_name = match object_type {
+            Type::Struct(struct_name) => struct_name,
+            _ => return None
+        };
+
+        self.structs.get(struct_name)
+            .and_then(|signature| signature.fields.iter().find(|(field, _)| *field == name))
+            .map(|(_, field_type)| field_type.clone())
+    }
+
+    /// Type of `module.name`, imported functions evaluate to function values
+    fn module_member(&mut self, key: &str, token: &Token) -> Type {
+        let module = &self.modules[key];
+        if module.functions.contains_key(&token.lexeme) {
+            return Type::Function;
+        }
+
+        match module.constants.get(&token.lexeme) {
+            Some(constant_type) => constant_type.clone(),
+            None => {
+                let message = format!("Module `{}` has no export `{}`", module.name, token.lexeme);
+                self.report(token.span, Code::UnresolvedImport, message);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_module_call(&mut self, key: &str, expr: &MethodCallExpr, argument_types: &[Type]) -> Type {
+        let module = &self.modules[key];
+        let qualified_name = format!("{}.{}", module.name, expr.name);
+
+        let (parameters, return_type) = match module.functions.get(&expr.name) {
+            Some(signature) => (signature.parameters.clone(), signature.return_type.clone()),
+            // a constant may hold a function value
+            None => return match self.module_member(key, &expr.token) {
+                Type::Function | Type::Unknown => Type::Unknown,
+                found => {
+                    self.report(expr.token.span, Code::UnsupportedOperation, format!("`{}` is not a function, found {}", qualified_name, found));
+                    Type::Unknown
+                }
+            }
+        };
+
+        self.check_arguments(expr.span, &qualified_name, &parameters, &expr.arguments, argument_types);
+        return_type
+    }
+
+    /// Type of the variable with this name, `None` if it is not bound in any scope
+    fn binding(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned())
+    }
+}
+
+
+impl ExpressionVisitor<Type> for TypeChecker {
+    fn accept_empty(&mut self, _expr: &EmptyExpr) -> Type {
+        Type::Empty
+    }
+
+    fn accept_const(&mut self, expr: &ConstExpr) -> Type {
+        let value_type = self.check_declaration(&expr.variable, expr.type_annotation.as_ref(), expr.value.as_ref());
+        self.scopes.last_mut().unwrap().insert(expr.variable.to_string(), value_type);
+
+        Type::Empty
+    }
+
+    /// Like at runtime a function declared inside a block is a local binding,
+    /// so its parameters are only checked at runtime. Top level functions were
+    /// declared before the module was checked.
+    fn accept_func(&mut self, expr: &Rc<FunctionExpr>) -> Type {
+        if expr.owner.is_none() && self.scopes.len() > 1 {
+            self.scopes.last_mut().unwrap().insert(expr.name.to_string(), Type::Function);
+        }
+
+        self.check_function_body(expr);
+
+        Type::Empty
+    }
+
+    fn accept_print(&mut self, expr: &PrintExpr) -> Type {
+        for value_expr in &expr.values {
+            value_expr.accept(self);
+        }
+
+        Type::Empty
+    }
+
+    fn accept_string(&mut self, _expr: &StringExpr) -> Type {
+        Type::String
+    }
+
+    fn accept_int(&mut self, _expr: &IntExpr) -> Type {
+        Type::Int
+    }
+
+    fn accept_float(&mut self, _expr: &FloatExpr) -> Type {
+        Type::Float
+    }
+
+    fn accept_bool(&mut self, _expr: &BoolExpr) -> Type {
+        Type::Bool
+    }
+
+    fn accept_variable(&mut self, expr: &VariableExpr) -> Type {
+        match self.binding(&expr.name) {
+            Some(variable_type) => variable_type,
+            None if self.function(None, &expr.name).is_some() => Type::Function,
+            None => Type::Unknown
+        }
+    }
+
+    fn accept_call(&mut self, expr: &CallExpr) -> Type {
+        let argument_types: Vec<Type> = expr.arguments.iter()
+            .map(|argument| argument.accept(self))
+            .collect();
+
+        // like at runtime a variable shadows functions, types and builtins of the same name,
+        // the parameters of a function value are not known
+        if let Some(found) = self.binding(&expr.name) {
+            if !Type::Function.accepts(&found) {
+                self.report(expr.token.span, Code::UnsupportedOperation, format!("`{}` is not a function, found {}", expr.name, found));
+            }
+
+            for (name, value) in &expr.named_arguments {
+                value.accept(self);
+                self.report(name.span, Code::InvalidCall, format!("Function `{}` does not take named arguments", expr.name));
+            }
+            return Type::Unknown;
+        }
+
+        if self.function(None, &expr.name).is_none() && self.structs.contains_key(&expr.name) {
+            return self.check_construction(expr, argument_types.len());
+        }
+
+        for (name, value) in &expr.named_arguments {
+            value.accept(self);
+            self.report(name.span, Code::InvalidCall, format!("Function `{}` does not take named arguments", expr.name));
+        }
+
+        let (parameters, return_type) = match self.function(None, &expr.name) {
+            Some(signature) => (signature.parameters, signature.return_type),
+            None => return self.check_builtin_call(expr, argument_types.len())
+        };
+
+        self.check_arguments(expr.span, &expr.name, &parameters, &expr.arguments, &argument_types);
+        return_type
+    }
+
+    fn accept_binary(&mut self, expr: &BinaryExpr) -> Type {
+        let left = expr.left.accept(self);
+        let right = expr.right.accept(self);
+
+        let result = match expr.operator.token_type {
+            TokenType::EqualEqual | TokenType::BangEqual => Some(Type::Bool),
+            _ if left == Type::Unknown || right == Type::Unknown => match expr.operator.token_type {
+                TokenType::PLUS | TokenType::MINUS | TokenType::STAR | TokenType::SLASH | TokenType::PERCENT => Some(Type::Unknown),
+                _ => Some(Type::Bool)
+            },
+            TokenType::AND | TokenType::OR => Some(Type::Bool),
+            TokenType::LESS | TokenType::LessEqual | TokenType::GREATER | TokenType::GreaterEqual => {
+                ((left.is_numeric() && right.is_numeric()) || (left == Type::String && right == Type::String))
+                    .then_some(Type::Bool)
+            },
+            TokenType::PLUS if left == Type::String || right == Type::String => Some(Type::String),
+            TokenType::PLUS if left == Type::List && right == Type::List => Some(Type::List),
+            _ if left == Type::Int && right == Type::Int => Some(Type::Int),
+            _ if left.is_numeric() && right.is_numeric() => Some(Type::Float),
+            _ => None
+        };
+
+        match result {
+            Some(result) => result,
+            None => {
+                self.report(expr.span(), Code::UnsupportedOperation, format!(
+                    "Unsupported operand types for `{}`: {} and {}", expr.operator.lexeme, left, right
+                ));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn accept_unary(&mut self, expr: &UnaryExpr) -> Type {
+        let operand = expr.operand.accept(self);
+
+        let result = match expr.operator.token_type {
+            _ if operand == Type::Unknown => Some(Type::Unknown),
+            TokenType::MINUS => operand.is_numeric().then_some(operand.clone()),
+            TokenType::NOT | TokenType::BANG => Some(Type::Bool),
+            _ => None
+        };
+
+        match result {
+            Some(result) => result,
+            None => {
+                self.report(expr.span(), Code::UnsupportedOperation, format!(
+                    "Unsupported operand type for `{}`: {}", expr.operator.lexeme, operand
+                ));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn accept_if(&mut self, expr: &IfExpr) -> Type {
+        expr.condition.accept(self);
+        self.check_block(&expr.body);
+        self.check_block(&expr.else_body);
+
+        Type::Empty
+    }
+
+    fn accept_while(&mut self, expr: &WhileExpr) -> Type {
+        expr.condition.accept(self);
+        self.check_block(&expr.body);
+
+        Type::Empty
+    }
+
+    fn accept_break(&mut self, _expr: &BreakExpr) -> Type {
+        Type::Empty
+    }
+
+    fn accept_continue(&mut self, _expr: &ContinueExpr) -> Type {
+        Type::Empty
+    }
+
+    fn accept_return(&mut self, expr: &ReturnExpr) -> Type {
+        let value_type = expr.value.accept(self);
+        let expected = self.return_types.last().cloned().unwrap_or(Type::Unknown);
+
+        // a bare return is fine in functions without declared return type only
+        let found = if value_type == Type::Empty && expected == Type::Unknown { Type::Unknown } else { value_type };
+
+        if !expected.accepts(&found) {
+            self.report(expr.span(), Code::MismatchedTypes, format!("Mismatched return type: expected {}, found {}", expected, found));
+        }
+
+        Type::Empty
+    }
+
+    fn accept_var(&mut self, expr: &VarExpr) -> Type {
+        let value_type = self.check_declaration(&expr.variable, expr.type_annotation.as_ref(), expr.value.as_ref());
+
+        // without annotation a variable may hold values of any type over time
+        let binding_type = if self.annotation_type(expr.type_annotation.as_ref()) == Type::Unknown { Type::Unknown } else { value_type };
+        self.scopes.last_mut().unwrap().insert(expr.variable.to_string(), binding_type);
+
+        Type::Empty
+    }
+
+    fn accept_assign(&mut self, expr: &AssignExpr) -> Type {
+        let target_type = expr.target.accept(self);
+        let value_type = expr.value.accept(self);
+
+        let result_type = match expr.operator.token_type {
+            TokenType::PlusEqual if target_type == Type::String => Type::String,
+            TokenType::PlusEqual | TokenType::MinusEqual => {
+                if target_type == Type::Unknown || value_type == Type::Unknown {
+                    Type::Unknown
+                } else if target_type == Type::Int && value_type == Type::Int {
+                    Type::Int
+                } else if target_type.is_numeric() && value_type.is_numeric() {
+                    Type::Float
+                } else {
+                    self.report(expr.span(), Code::UnsupportedOperation, format!(
+                        "Unsupported operand types for `{}`: {} and {}", expr.operator.lexeme, target_type, value_type
+                    ));
+                    return Type::Empty;
+                }
+            },
+            _ => value_type
+        };
+
+        if !target_type.accepts(&result_type) {
+            self.report(expr.value.span(), Code::MismatchedTypes, format!("Mismatched types in assignment: expected {}, found {}", target_type, result_type));
+        }
+
+        Type::Empty
+    }
+
+    fn accept_list(&mut self, expr: &ListExpr) -> Type {
+        for element in &expr.elements {
+            element.accept(self);
+        }
+
+        Type::List
+    }
+
+    /// Elements of lists and maps can have any type, the character of a string is a string
+    fn accept_index(&mut self, expr: &IndexExpr) -> Type {
+        let object_type = expr.object.accept(self);
+
+        match object_type {
+            Type::Map => {
+                let key_type = expr.index.accept(self);
+                if !key_type.is_map_key() {
+                    self.report(expr.token.span, Code::UnsupportedOperation, format!("A {} cannot be used as map key", key_type));
+                }
+
+                Type::Unknown
+            },
+            Type::Unknown => {
+                expr.index.accept(self);
+                Type::Unknown
+            },
+            Type::List | Type::String => {
+                self.check_index_type(expr.index.as_ref(), &expr.token);
+                if object_type == Type::String { Type::String } else { Type::Unknown }
+            },
+            _ => {
+                self.report(expr.token.span, Code::UnsupportedOperation, format!("Cannot index into {}", object_type));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn accept_slice(&mut self, expr: &SliceExpr) -> Type {
+        let object_type = self.check_sequence(expr.object.as_ref(), &expr.token);
+        self.check_index_type(expr.start.as_ref(), &expr.token);
+        self.check_index_type(expr.end.as_ref(), &expr.token);
+
+        object_type
+    }
+
+    fn accept_for(&mut self, expr: &ForExpr) -> Type {
+        let iterable_type = expr.iterable.accept(self);
+        if !iterable_type.is_sequence() && iterable_type != Type::Map {
+            self.report(expr.token.span, Code::UnsupportedOperation, format!("Cannot iterate over {}", iterable_type));
+        }
+
+        let element_type = if iterable_type == Type::String { Type::String } else { Type::Unknown };
+        let mut scope = HashMap::new();
+
+        match &expr.value_variable {
+            Some(value_variable) => {
+                let index_type = if matches!(iterable_type, Type::List | Type::String) { Type::Int } else { Type::Unknown };
+                scope.insert(expr.variable.to_string(), index_type);
+                scope.insert(value_variable.lexeme.to_string(), element_type);
+            },
+            None => {
+                scope.insert(expr.variable.to_string(), element_type);
+            }
+        }
+
+        self.scopes.push(scope);
+        self.check_body(&expr.body);
+        self.scopes.pop();
+
+        Type::Empty
+    }
+
+    fn accept_struct(&mut self, expr: &Rc<StructExpr>) -> Type {
+        for field in &expr.fields {
+            self.check_annotation(field.type_annotation.as_ref());
+        }
+
+        Type::Empty
+    }
+
+    fn accept_get(&mut self, expr: &GetExpr) -> Type {
+        if let Some(enum_name) = self.enum_of(expr.object.as_ref()) {
+            return self.check_variant_construction(&enum_name, &expr.token, expr.span(), &[], &[]);
+        }
+
+        let object_type = expr.object.accept(self);
+
+        let name = match &object_type {
+            Type::Unknown => return Type::Unknown,
+            Type::Module(key) => return self.module_member(key, &expr.token),
+            Type::Struct(name) => name,
+            _ => {
+                self.report(expr.token.span, Code::UnsupportedOperation, format!("Cannot access field `{}` of {}", expr.name, object_type));
+                return Type::Unknown;
+            }
+        };
+
+        match self.field_type(&object_type, &expr.name) {
+            Some(field_type) => field_type,
+            None => {
+                self.report(expr.token.span, Code::UnknownName, format!("Struct `{}` has no field `{}`", name, expr.name));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn accept_enum(&mut self, expr: &Rc<EnumExpr>) -> Type {
+        for variant in &expr.variants {
+            for field in &variant.fields {
+                self.check_annotation(field.type_annotation.as_ref());
+            }
+        }
+
+        Type::Empty
+    }
+
+    fn accept_method_call(&mut self, expr: &MethodCallExpr) -> Type {
+        let type_name = self.type_of(expr.object.as_ref());
+        let object_type = match &type_name {
+            Some(..) => Type::Unknown,
+            None => expr.object.accept(self)
+        };
+
+        let argument_types: Vec<Type> = expr.arguments.iter()
+            .map(|argument| argument.accept(self))
+            .collect();
+
+        if let Some(type_name) = type_name {
+            return self.check_type_call(&type_name, expr, &argument_types);
+        }
+
+        if let Type::Module(key) = &object_type {
+            return self.check_module_call(key, expr, &argument_types);
+        }
+
+        let owner = match &object_type {
+            Type::Unknown => return Type::Unknown,
+            Type::Struct(name) | Type::Enum(name) => Some(name.as_str()),
+            _ => None
+        };
+
+        let qualified_name = format!("{}.{}", object_type, expr.name);
+        let (parameters, return_type) = match owner.and_then(|owner| self.function(Some(owner), &expr.name)) {
+            Some(signature) if signature.is_method => (signature.parameters[1..].to_vec(), signature.return_type),
+            Some(signature) => {
+                self.report(expr.token.span, Code::InvalidCall, format!("`{}` has no `self` parameter and has to be called on the type", qualified_name));
+                return signature.return_type;
+            },
+            // a struct field holding a function is called like a method
+            None if matches!(self.field_type(&object_type, &expr.name), Some(Type::Function) | Some(Type::Unknown)) => return Type::Unknown,
+            None => {
+                self.report(expr.token.span, Code::UnknownName, format!("No method `{}` on {}", expr.name, object_type));
+                return Type::Unknown;
+            }
+        };
+
+        self.check_arguments(expr.span, &qualified_name, &parameters, &expr.arguments, &argument_types);
+        return_type
+    }
+
+    fn accept_lambda(&mut self, expr: &LambdaExpr) -> Type {
+        self.check_function_body(&expr.function);
+        Type::Function
+    }
+
+    fn accept_apply(&mut self, expr: &ApplyExpr) -> Type {
+        let callee_type = expr.callee.accept(self);
+        for argument in &expr.arguments {
+            argument.accept(self);
+        }
+
+        if !Type::Function.accepts(&callee_type) {
+            self.report(expr.token.span, Code::UnsupportedOperation, format!("Cannot call a value of type {}", callee_type));
+        }
+
+        Type::Unknown
+    }
+
+    fn accept_import(&mut self, expr: &ImportExpr) -> Type {
+        // modules which could not be loaded have been reported already
+        let key = match modules::module_key(&expr.module.file_name, &expr.module.lexeme) {
+            Some(key) if self.modules.contains_key(&key) => key,
+            _ => return Type::Empty
+        };
+
+        if expr.names.is_empty() {
+            self.scopes.last_mut().unwrap().insert(expr.module.lexeme.to_string(), Type::Module(key.to_string()));
+        }
+
+        for name in &expr.names {
+            let module = &self.modules[&key];
+            if let Some(signature) = module.functions.get(&name.lexeme).cloned() {
+                if self.function(None, &name.lexeme).is_some() {
+                    self.report(name.span, Code::DuplicateDeclaration, format!("`{}` is already declared in this module", name.lexeme));
+                }
+                self.functions.entry(None).or_default().insert(name.lexeme.to_string(), signature);
+            } else {
+                let member_type = self.module_member(&key, name);
+                self.scopes.last_mut().unwrap().insert(name.lexeme.to_string(), member_type);
+            }
+        }
+
+        Type::Empty
+    }
+
+    fn accept_impl(&mut self, expr: &ImplExpr) -> Type {
+        match self.resolve_type(&expr.type_name) {
+            Some(Type::Struct(..)) | Some(Type::Enum(..)) => {},
+            _ => {
+                self.report(expr.token.span, Code::UnsupportedOperation, format!("Cannot implement functions for `{}`, it is no struct or enum", expr.type_name));
+                return Type::Empty;
+            }
+        }
+
+        for function in &expr.functions {
+            self.accept_func(function);
+        }
+
+        Type::Empty
+    }
+
+    /// The type of a match is the type shared by the last expression of all arms, if there is one
+    fn accept_match(&mut self, expr: &MatchExpr) -> Type {
+        let subject_type = expr.subject.accept(self);
+        let mut arm_types: Vec<Type> = vec![];
+
+        for arm in &expr.arms {
+            self.scopes.push(HashMap::new());
+            self.check_pattern(&arm.pattern, &subject_type);
+            arm.guard.accept(self);
+
+            let mut arm_type = Type::Empty;
+            for body_expr in &arm.body {
+                arm_type = body_expr.accept(self);
+            }
+
+            self.scopes.pop();
+            arm_types.push(arm_type);
+        }
+
+        self.check_exhaustive(expr, &subject_type);
+
+        match arm_types.first() {
+            Some(first) if arm_types.iter().all(|arm_type| arm_type == first) => first.clone(),
+            _ => Type::Unknown
+        }
+    }
+
+    fn accept_map(&mut self, expr: &MapExpr) -> Type {
+        for (key, value) in &expr.entries {
+            let key_type = key.accept(self);
+            if !key_type.is_map_key() {
+                self.report(expr.token.span, Code::UnsupportedOperation, format!("A {} cannot be used as map key", key_type));
+            }
+
+            value.accept(self);
+        }
+
+        Type::Map
+    }
+
+    fn accept_type(&mut self, expr: &TypeExpr) -> Type {
+        match self.resolve_type(&expr.name) {
+            Some(found) => found,
+            None => {
+                self.report(expr.token.span, Code::UnknownName, format!("Unknown type `{}`", expr.name));
+                Type::Unknown
+            }
+        }
+    }
+}
+
+/// Types of other modules are unknown to the importing module
+fn exported_type(exported: Type) -> Type {
+    match exported {
+        Type::Struct(..) | Type::Enum(..) => Type::Unknown,
+        other => other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::reporter::ErrorFormat;
+    use crate::scanner::Scanner;
+
+    fn error_count(source: &str) -> usize {
+        let reporter = CodeReporter::new(ErrorFormat::Muted);
+        let tokens = Scanner::new(reporter.clone()).scan_string(String::from("main.hunt"), source.to_string());
+        let ast = Parser::new(tokens, reporter.clone()).parse_ast();
+        TypeChecker::new(reporter.clone()).check_ast(&ast);
+
+        reporter.error_count()
+    }
+
+    #[test]
+    fn functions_are_declared_once_per_module() {
+        assert_eq!(error_count("fun f()\n    return 1\nfun f()\n    return 2\n"), 1);
+        assert_eq!(error_count("fun f()\n    return 1\nfun g()\n    return 2\n"), 0);
+    }
+
+    #[test]
+    fn types_are_declared_once_per_module() {
+        assert_eq!(error_count("struct P\n    x\nstruct P\n    y\nconst p = P(x=1)\n"), 1);
+        assert_eq!(error_count("struct P\n    x\nstruct P\n    y\nconst p = P(y=1)\n"), 3);
+        assert_eq!(error_count("enum E\n    A\nenum E\n    B\n"), 1);
+        assert_eq!(error_count("struct P\n    x\nenum P\n    A\n"), 1);
+        assert_eq!(error_count("struct P\n    x\nenum E\n    A\n"), 0);
+    }
+
+    #[test]
+    fn functions_are_declared_once_per_type() {
+        let point = "struct Point\n    x\n";
+        assert_eq!(error_count(&format!("{}impl Point\n    fun a(self)\n        return 1\nimpl Point\n    fun a(self)\n        return 2\n", point)), 1);
+        assert_eq!(error_count(&format!("{}fun a()\n    return 1\nimpl Point\n    fun a(self)\n        return 2\n", point)), 0);
+    }
+}
@@ -0,0 +1,200 @@
+/// Kind of a diagnostic. Every kind has a code like `H0007`, which is shown with the
+/// diagnostic and whose long form description is printed by `--explain H0007`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Code {
+    InvalidString,
+    SyntaxError,
+    UnresolvedImport,
+    ImportCycle,
+    DuplicateDeclaration,
+    UnknownName,
+    MismatchedTypes,
+    ArgumentCount,
+    UnsupportedOperation,
+    InvalidCall,
+    NonExhaustiveMatch,
+    RuntimeError,
+}
+
+impl Code {
+    pub const ALL: [Code; 12] = [
+        Code::InvalidString,
+        Code::SyntaxError,
+        Code::UnresolvedImport,
+        Code::ImportCycle,
+        Code::DuplicateDeclaration,
+        Code::UnknownName,
+        Code::MismatchedTypes,
+        Code::ArgumentCount,
+        Code::UnsupportedOperation,
+        Code::InvalidCall,
+        Code::NonExhaustiveMatch,
+        Code::RuntimeError,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Code::InvalidString => "H0001",
+            Code::SyntaxError => "H0002",
+            Code::UnresolvedImport => "H0003",
+            Code::ImportCycle => "H0004",
+            Code::DuplicateDeclaration => "H0005",
+            Code::UnknownName => "H0006",
+            Code::MismatchedTypes => "H0007",
+            Code::ArgumentCount => "H0008",
+            Code::UnsupportedOperation => "H0009",
+            Code::InvalidCall => "H0010",
+            Code::NonExhaustiveMatch => "H0011",
+            Code::RuntimeError => "H0012",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Code> {
+        Code::ALL.iter().find(|code| code.name().eq_ignore_ascii_case(name)).copied()
+    }
+
+    /// Long form description printed by `--explain`
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Code::InvalidString => "\
+A string literal could not be read.
+
+Strings have to end on the line they start on, only strings in triple quotes
+may span several lines, and a line break cannot be escaped. Escape sequences
+are limited to `\\n`, `\\t`, `\\r`, `\\0`, `\\\"`, `\\\\`, `\\{`, `\\}` and `\\u{XXXX}` with
+one to six hex digits.
+
+    const a = \"unterminated
+    const b = \"\\q\"
+
+Close the string on the same line or use `\"\"\"` quotes, and escape the backslash
+of an unknown sequence as `\\\\`.",
+            Code::SyntaxError => "\
+The parser could not make sense of a line.
+
+This covers characters which are not part of the language, missing parentheses,
+commas or brackets, blocks which are not indented deeper than their header,
+`break` or `continue` outside of a loop and structs, enums, impl blocks or
+imports inside a block.
+
+    fun add(a, b
+        return a + b
+
+Add what the message says is missing, here the `)` after the parameters.",
+            Code::UnresolvedImport => "\
+An import or an imported name could not be resolved.
+
+`import name` loads `name.hunt` from the directory of the importing file.
+Only top level functions and constants of a module can be imported, types
+stay private to their module. A name can only be imported once per module.
+
+    from utils import helper
+
+Check that `utils.hunt` exists next to the importing file and that it declares
+a top level `helper`.",
+            Code::ImportCycle => "\
+Modules import each other in a cycle.
+
+A module is checked and executed after all modules it imports, which is
+impossible if a module imports itself, directly or through other modules.
+
+    # a.hunt
+    import b
+    # b.hunt
+    import a
+
+Move the code both modules need into a third module which imports neither.",
+            Code::DuplicateDeclaration => "\
+A name is declared twice in the same scope.
+
+This also covers two fields of a struct or variant, two variants of an enum,
+two parameters of a function, two functions of an impl block, a name imported
+twice and a name bound twice in one match pattern.
+
+    const limit = 10
+    const limit = 20
+
+Use a different name, or declare the first binding with `var` and assign to it.",
+            Code::UnknownName => "\
+A type, enum variant, field, method or module member does not exist.
+
+    struct Point
+        x: int
+        y: int
+
+    const p = Point(x=1, y=2)
+    print(p.z)
+
+Check the spelling against the declaration the name refers to.",
+            Code::MismatchedTypes => "\
+A value does not have the type its use requires.
+
+This is reported for annotated declarations, arguments, return values,
+assignments, struct fields, enum values and match patterns. Ints are not
+promoted to floats outside of arithmetic.
+
+    const ratio: float = 1
+
+Write the value with the expected type, like `1.0`, or change the annotation.",
+            Code::ArgumentCount => "\
+A call, construction or pattern has a different number of values than declared.
+
+    fun add(a, b)
+        return a + b
+
+    print(add(1))
+
+Pass exactly one value per parameter. Structs need a value for every field,
+methods take their `self` parameter from the instance they are called on.",
+            Code::UnsupportedOperation => "\
+An operation is used with values which do not support it.
+
+Examples are arithmetic on strings and bools, indexing into a number,
+iterating over a struct, maps keyed by floats and calling a value which is
+no function.
+
+    const total = \"sum: \" * 2
+
+Convert the values first or use an operation their types support.",
+            Code::InvalidCall => "\
+A function or constructor is called in a way it does not support.
+
+Structs are constructed with named fields only, functions take positional
+arguments only. Methods have to be called on an instance, functions of a type
+without `self` parameter on the type.
+
+    struct Point
+        x: int
+        y: int
+
+    const p = Point(1, 2)
+
+Name the fields: `Point(x=1, y=2)`.",
+            Code::NonExhaustiveMatch => "\
+A `match` does not handle every possible value.
+
+This is only a warning, as the missing values may never occur. If one of them
+does occur while the program runs, it stops with a runtime error.
+
+    enum Shape
+        Circle(radius: float)
+        Square(side: float)
+
+    match shape
+        Shape.Circle(radius) -> print(radius)
+
+Add an arm for every variant, or a `_` arm which handles the rest.",
+            Code::RuntimeError => "\
+The program failed while running.
+
+Examples are division by zero, an index out of range, a missing map key, an
+int overflow, assigning to a constant and calls nested too deep. The functions
+the error passed through are listed below the message, innermost first.
+
+    const values = [1, 2, 3]
+    print(values[3])
+
+Check the values involved, for example with `len` before indexing.",
+        }
+    }
+}
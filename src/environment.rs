@@ -0,0 +1,68 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::value::Value;
+
+struct Binding {
+    value: Value,
+    mutable: bool,
+}
+
+pub enum AssignmentError {
+    Undefined,
+    Constant,
+}
+
+/// One lexical scope of bindings.
+///
+/// Function bodies, `if`/`else` branches and every iteration of a loop body
+/// run in a fresh scope whose parent is the scope they are written in, so a
+/// function keeps seeing the scope it was declared in after that scope was left.
+/// A binding may shadow a binding of the same name in any parent scope, but a
+/// name can only be defined once per scope.
+pub struct Environment {
+    values: HashMap<String, Binding>,
+    parent: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Environment { values: HashMap::new(), parent: None }))
+    }
+
+    pub fn with_parent(parent: Rc<RefCell<Environment>>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Environment { values: HashMap::new(), parent: Some(parent) }))
+    }
+
+    /// Adds a binding to this scope, returns false if the name is already taken in it
+    pub fn define(&mut self, name: String, value: Value, mutable: bool) -> bool {
+        if self.values.contains_key(&name) {
+            return false;
+        }
+
+        self.values.insert(name, Binding { value, mutable });
+        true
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(binding) => Some(binding.value.clone()),
+            None => self.parent.as_ref().and_then(|parent| parent.borrow().get(name))
+        }
+    }
+
+    /// Replaces the value of the closest binding with this name, which has to be mutable
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), AssignmentError> {
+        match self.values.get_mut(name) {
+            Some(binding) if binding.mutable => {
+                binding.value = value;
+                Ok(())
+            },
+            Some(..) => Err(AssignmentError::Constant),
+            None => match &self.parent {
+                Some(parent) => parent.borrow_mut().assign(name, value),
+                None => Err(AssignmentError::Undefined)
+            }
+        }
+    }
+}
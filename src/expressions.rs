@@ -1,126 +1,1170 @@
+use std::rc::Rc;
+use crate::scanner::{Span, Token};
 
-pub trait ExpressionVisitor {
-    fn accept_empty(&self, expr: &EmptyExpr);
-    fn accept_const(&self, expr: &ConstExpr);
-    fn accept_func(&self, expr: &FunctionExpr);
-    fn accept_print(&self, expr: &PrintExpr);
-    fn accept_string(&self, expr: &StringExpr);
-    fn accept_int(&self, expr: &IntExpr);
-    fn accept_variable(&self, expr: &VariableExpr);
+pub trait ExpressionVisitor<T> {
+    fn accept_empty(&mut self, expr: &EmptyExpr) -> T;
+    fn accept_const(&mut self, expr: &ConstExpr) -> T;
+    fn accept_func(&mut self, expr: &Rc<FunctionExpr>) -> T;
+    fn accept_print(&mut self, expr: &PrintExpr) -> T;
+    fn accept_string(&mut self, expr: &StringExpr) -> T;
+    fn accept_int(&mut self, expr: &IntExpr) -> T;
+    fn accept_float(&mut self, expr: &FloatExpr) -> T;
+    fn accept_bool(&mut self, expr: &BoolExpr) -> T;
+    fn accept_variable(&mut self, expr: &VariableExpr) -> T;
+    fn accept_call(&mut self, expr: &CallExpr) -> T;
+    fn accept_binary(&mut self, expr: &BinaryExpr) -> T;
+    fn accept_unary(&mut self, expr: &UnaryExpr) -> T;
+    fn accept_if(&mut self, expr: &IfExpr) -> T;
+    fn accept_while(&mut self, expr: &WhileExpr) -> T;
+    fn accept_break(&mut self, expr: &BreakExpr) -> T;
+    fn accept_continue(&mut self, expr: &ContinueExpr) -> T;
+    fn accept_return(&mut self, expr: &ReturnExpr) -> T;
+    fn accept_type(&mut self, expr: &TypeExpr) -> T;
+    fn accept_var(&mut self, expr: &VarExpr) -> T;
+    fn accept_assign(&mut self, expr: &AssignExpr) -> T;
+    fn accept_list(&mut self, expr: &ListExpr) -> T;
+    fn accept_index(&mut self, expr: &IndexExpr) -> T;
+    fn accept_slice(&mut self, expr: &SliceExpr) -> T;
+    fn accept_for(&mut self, expr: &ForExpr) -> T;
+    fn accept_map(&mut self, expr: &MapExpr) -> T;
+    fn accept_struct(&mut self, expr: &Rc<StructExpr>) -> T;
+    fn accept_get(&mut self, expr: &GetExpr) -> T;
+    fn accept_enum(&mut self, expr: &Rc<EnumExpr>) -> T;
+    fn accept_method_call(&mut self, expr: &MethodCallExpr) -> T;
+    fn accept_match(&mut self, expr: &MatchExpr) -> T;
+    fn accept_impl(&mut self, expr: &ImplExpr) -> T;
+    fn accept_lambda(&mut self, expr: &LambdaExpr) -> T;
+    fn accept_apply(&mut self, expr: &ApplyExpr) -> T;
+    fn accept_import(&mut self, expr: &ImportExpr) -> T;
 }
 
+/// Borrowed view of a concrete expression node.
+///
+/// `Expression` has to stay object safe, so it cannot offer a generic `accept`.
+/// Every node exposes itself through this enum instead and `accept` dispatches on it.
+pub enum ExpressionKind<'a> {
+    Empty(&'a EmptyExpr),
+    Const(&'a ConstExpr),
+    Func(&'a Rc<FunctionExpr>),
+    Print(&'a PrintExpr),
+    String(&'a StringExpr),
+    Int(&'a IntExpr),
+    Float(&'a FloatExpr),
+    Bool(&'a BoolExpr),
+    Variable(&'a VariableExpr),
+    Call(&'a CallExpr),
+    Binary(&'a BinaryExpr),
+    Unary(&'a UnaryExpr),
+    If(&'a IfExpr),
+    While(&'a WhileExpr),
+    Break(&'a BreakExpr),
+    Continue(&'a ContinueExpr),
+    Return(&'a ReturnExpr),
+    Type(&'a TypeExpr),
+    Var(&'a VarExpr),
+    Assign(&'a AssignExpr),
+    List(&'a ListExpr),
+    Index(&'a IndexExpr),
+    Slice(&'a SliceExpr),
+    For(&'a ForExpr),
+    Map(&'a MapExpr),
+    Struct(&'a Rc<StructExpr>),
+    Get(&'a GetExpr),
+    Enum(&'a Rc<EnumExpr>),
+    MethodCall(&'a MethodCallExpr),
+    Match(&'a MatchExpr),
+    Impl(&'a ImplExpr),
+    Lambda(&'a LambdaExpr),
+    Apply(&'a ApplyExpr),
+    Import(&'a ImportExpr),
+}
+
+#[derive(Clone)]
 pub struct EmptyExpr{}
 
+/// Functions declared in an `impl` block carry the name of their type as owner.
+/// Declarations live behind an `Rc` in the tree, so closures and the definitions
+/// of a module share the parsed node instead of copying it.
 pub struct FunctionExpr {
     pub name: String,
+    /// Name of the function, `fun` for lambdas
+    pub token: Token,
+    pub owner: Option<String>,
+    pub parameters: Vec<Parameter>,
     pub return_type: Box<dyn Expression>,
     pub body: Vec<Box<dyn Expression>>,
 }
 
+impl FunctionExpr {
+    /// Methods are called on an instance, which is passed as their first parameter `self`
+    pub fn is_method(&self) -> bool {
+        self.owner.is_some() && self.parameters.first().is_some_and(|parameter| parameter.name == "self")
+    }
+
+    /// Name for messages and traces, `Point.distance` for functions of a type
+    pub fn qualified_name(&self) -> String {
+        match &self.owner {
+            Some(owner) => format!("{}.{}", owner, self.name),
+            None => self.name.to_string()
+        }
+    }
+}
+
+/// `impl Type` followed by indented function declarations
+#[derive(Clone)]
+pub struct ImplExpr {
+    pub type_name: String,
+    pub token: Token,
+    pub functions: Vec<Rc<FunctionExpr>>,
+}
+
+/// Anonymous function `fun (x) -> x + 1`, the body after the arrow is returned
+#[derive(Clone)]
+pub struct LambdaExpr {
+    pub token: Token,
+    pub function: Rc<FunctionExpr>,
+}
+
+/// Call of a function value which is not referenced by name, like `make_adder(1)(2)`
+#[derive(Clone)]
+pub struct ApplyExpr {
+    pub callee: Box<dyn Expression>,
+    pub token: Token,
+    pub arguments: Vec<Box<dyn Expression>>,
+    pub span: Span,
+}
+
+/// `import utils` binds the module itself, `from utils import a, b` binds the listed
+/// functions and constants of the module. `names` is empty for the first form.
+#[derive(Clone)]
+pub struct ImportExpr {
+    pub module: Token,
+    pub names: Vec<Token>,
+}
+
+/// Parameters and bindings without annotation carry an `EmptyExpr` as type.
+#[derive(Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub type_annotation: Box<dyn Expression>,
+}
+
+pub struct StructExpr {
+    pub name: String,
+    pub token: Token,
+    pub fields: Vec<Field>,
+}
+
+/// Fields without annotation carry an `EmptyExpr` as type.
+#[derive(Clone)]
+pub struct Field {
+    pub name: String,
+    pub token: Token,
+    pub type_annotation: Box<dyn Expression>,
+}
+
+pub struct EnumExpr {
+    pub name: String,
+    pub token: Token,
+    pub variants: Vec<Variant>,
+}
+
+/// Variant of an enum, the fields of `Circle(radius: float)` are passed by position
+#[derive(Clone)]
+pub struct Variant {
+    pub name: String,
+    pub token: Token,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Clone)]
 pub struct PrintExpr {
-    pub values: Vec<Box<dyn Expression>>
+    pub values: Vec<Box<dyn Expression>>,
+    pub span: Span,
 }
 
+#[derive(Clone)]
 pub struct ConstExpr {
     pub variable: String,
+    pub token: Token,
+    pub type_annotation: Box<dyn Expression>,
+    pub value: Box<dyn Expression>
+}
+
+/// Mutable counterpart of `ConstExpr`
+#[derive(Clone)]
+pub struct VarExpr {
+    pub variable: String,
+    pub token: Token,
+    pub type_annotation: Box<dyn Expression>,
     pub value: Box<dyn Expression>
 }
 
+/// `target = value`, `target += value` or `target -= value`
+#[derive(Clone)]
+pub struct AssignExpr {
+    pub target: Box<dyn Expression>,
+    pub operator: Token,
+    pub value: Box<dyn Expression>,
+}
+
+#[derive(Clone)]
 pub struct StringExpr {
     pub value: String,
+    pub span: Span,
 }
 
+#[derive(Clone)]
 pub struct VariableExpr {
     pub name: String,
+    pub token: Token,
 }
 
+#[derive(Clone)]
 pub struct IntExpr {
     pub value: i64,
+    pub span: Span,
+}
+
+#[derive(Clone)]
+pub struct FloatExpr {
+    pub value: f64,
+    pub span: Span,
+}
+
+#[derive(Clone)]
+pub struct BoolExpr {
+    pub value: bool,
+    pub span: Span,
+}
+
+/// Calls a function or constructs a struct, structs take `name=value` arguments only
+#[derive(Clone)]
+pub struct CallExpr {
+    pub name: String,
+    pub token: Token,
+    pub arguments: Vec<Box<dyn Expression>>,
+    pub named_arguments: Vec<(Token, Box<dyn Expression>)>,
+    pub span: Span,
+}
+
+#[derive(Clone)]
+pub struct BinaryExpr {
+    pub left: Box<dyn Expression>,
+    pub operator: Token,
+    pub right: Box<dyn Expression>,
+}
+
+#[derive(Clone)]
+pub struct UnaryExpr {
+    pub operator: Token,
+    pub operand: Box<dyn Expression>,
+}
+
+/// An `else if` chain is stored as an `else_body` holding only the next `IfExpr`.
+#[derive(Clone)]
+pub struct IfExpr {
+    pub condition: Box<dyn Expression>,
+    pub body: Vec<Box<dyn Expression>>,
+    pub else_body: Vec<Box<dyn Expression>>,
+}
+
+#[derive(Clone)]
+pub struct WhileExpr {
+    pub condition: Box<dyn Expression>,
+    pub body: Vec<Box<dyn Expression>>,
+}
+
+/// `for variable in iterable` walks over the elements of a list, the characters of a string
+/// or the keys of a map. With `for variable, value_variable in iterable` the first variable
+/// holds the index or key and the second one the element or value.
+#[derive(Clone)]
+pub struct ForExpr {
+    pub variable: String,
+    pub token: Token,
+    pub value_variable: Option<Token>,
+    pub iterable: Box<dyn Expression>,
+    pub body: Vec<Box<dyn Expression>>,
+}
+
+#[derive(Clone)]
+pub struct BreakExpr {
+    pub keyword: Token,
+}
+
+#[derive(Clone)]
+pub struct ContinueExpr {
+    pub keyword: Token,
+}
+
+/// A bare `return` carries an `EmptyExpr` as value.
+#[derive(Clone)]
+pub struct ReturnExpr {
+    pub keyword: Token,
+    pub value: Box<dyn Expression>,
+}
+
+#[derive(Clone)]
+pub struct ListExpr {
+    pub elements: Vec<Box<dyn Expression>>,
+    pub span: Span,
+}
+
+/// `{key: value, ...}`, the token is the opening brace
+#[derive(Clone)]
+pub struct MapExpr {
+    pub entries: Vec<(Box<dyn Expression>, Box<dyn Expression>)>,
+    pub token: Token,
+    pub span: Span,
+}
+
+/// `object[index]`, the token is the opening bracket
+#[derive(Clone)]
+pub struct IndexExpr {
+    pub object: Box<dyn Expression>,
+    pub index: Box<dyn Expression>,
+    pub token: Token,
+    pub span: Span,
+}
+
+/// `object[start:end]`, omitted bounds are stored as `EmptyExpr`
+#[derive(Clone)]
+pub struct SliceExpr {
+    pub object: Box<dyn Expression>,
+    pub start: Box<dyn Expression>,
+    pub end: Box<dyn Expression>,
+    pub token: Token,
+    pub span: Span,
+}
+
+/// `object.name`, the token is the field name
+#[derive(Clone)]
+pub struct GetExpr {
+    pub object: Box<dyn Expression>,
+    pub name: String,
+    pub token: Token,
+}
+
+/// `object.name(arguments)`, which also constructs enum variants like `Shape.Circle(1.0)`
+#[derive(Clone)]
+pub struct MethodCallExpr {
+    pub object: Box<dyn Expression>,
+    pub name: String,
+    pub token: Token,
+    pub arguments: Vec<Box<dyn Expression>>,
+    pub span: Span,
+}
+
+/// `match subject` followed by indented `pattern if guard -> body` arms.
+/// The value of the match is the value of the last expression of the chosen arm.
+#[derive(Clone)]
+pub struct MatchExpr {
+    pub keyword: Token,
+    pub subject: Box<dyn Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+/// Arms without guard carry an `EmptyExpr` as guard.
+#[derive(Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Box<dyn Expression>,
+    pub body: Vec<Box<dyn Expression>>,
+}
+
+#[derive(Clone)]
+pub enum Pattern {
+    /// `_` matches anything without binding it
+    Wildcard,
+    /// A name matches anything and binds it for the guard and the body
+    Binding(Token),
+    /// Int, float, string or bool literal compared with `==`
+    Literal(Box<dyn Expression>),
+    /// `Enum.Variant(patterns...)`, the field patterns are matched by position
+    Variant { enum_name: Token, variant: Token, fields: Vec<Pattern> },
+}
+
+impl Pattern {
+    /// Whether the pattern matches every value
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Binding(..))
+    }
+
+    pub fn dump(&self) -> String {
+        match self {
+            Pattern::Wildcard => String::from("_"),
+            Pattern::Binding(name) => name.lexeme.to_string(),
+            Pattern::Literal(value) => value.dump(),
+            Pattern::Variant { enum_name, variant, fields } => {
+                let fields: Vec<String> = fields.iter().map(|field| field.dump()).collect();
+                format!("{}.{}({})", enum_name.lexeme, variant.lexeme, fields.join(", "))
+            }
+        }
+    }
+}
+
+/// Type annotation like the `int` in `const x: int = 1`
+#[derive(Clone)]
+pub struct TypeExpr {
+    pub name: String,
+    pub token: Token,
 }
 
 pub trait Expression {
-    fn accept(&self, visitor: &dyn ExpressionVisitor);
+    fn kind(&self) -> ExpressionKind<'_>;
     fn dump(&self) -> String;
+    /// Part of the source the expression was parsed from
+    fn span(&self) -> Span;
+    fn box_clone(&self) -> Box<dyn Expression>;
+}
+
+impl dyn Expression + '_ {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> T {
+        match self.kind() {
+            ExpressionKind::Empty(expr) => visitor.accept_empty(expr),
+            ExpressionKind::Const(expr) => visitor.accept_const(expr),
+            ExpressionKind::Func(expr) => visitor.accept_func(expr),
+            ExpressionKind::Print(expr) => visitor.accept_print(expr),
+            ExpressionKind::String(expr) => visitor.accept_string(expr),
+            ExpressionKind::Int(expr) => visitor.accept_int(expr),
+            ExpressionKind::Float(expr) => visitor.accept_float(expr),
+            ExpressionKind::Bool(expr) => visitor.accept_bool(expr),
+            ExpressionKind::Variable(expr) => visitor.accept_variable(expr),
+            ExpressionKind::Call(expr) => visitor.accept_call(expr),
+            ExpressionKind::Binary(expr) => visitor.accept_binary(expr),
+            ExpressionKind::Unary(expr) => visitor.accept_unary(expr),
+            ExpressionKind::If(expr) => visitor.accept_if(expr),
+            ExpressionKind::While(expr) => visitor.accept_while(expr),
+            ExpressionKind::Break(expr) => visitor.accept_break(expr),
+            ExpressionKind::Continue(expr) => visitor.accept_continue(expr),
+            ExpressionKind::Return(expr) => visitor.accept_return(expr),
+            ExpressionKind::Type(expr) => visitor.accept_type(expr),
+            ExpressionKind::Var(expr) => visitor.accept_var(expr),
+            ExpressionKind::Assign(expr) => visitor.accept_assign(expr),
+            ExpressionKind::List(expr) => visitor.accept_list(expr),
+            ExpressionKind::Index(expr) => visitor.accept_index(expr),
+            ExpressionKind::Slice(expr) => visitor.accept_slice(expr),
+            ExpressionKind::For(expr) => visitor.accept_for(expr),
+            ExpressionKind::Map(expr) => visitor.accept_map(expr),
+            ExpressionKind::Struct(expr) => visitor.accept_struct(expr),
+            ExpressionKind::Get(expr) => visitor.accept_get(expr),
+            ExpressionKind::Enum(expr) => visitor.accept_enum(expr),
+            ExpressionKind::MethodCall(expr) => visitor.accept_method_call(expr),
+            ExpressionKind::Match(expr) => visitor.accept_match(expr),
+            ExpressionKind::Impl(expr) => visitor.accept_impl(expr),
+            ExpressionKind::Lambda(expr) => visitor.accept_lambda(expr),
+            ExpressionKind::Apply(expr) => visitor.accept_apply(expr),
+            ExpressionKind::Import(expr) => visitor.accept_import(expr),
+        }
+    }
+}
+
+impl Clone for Box<dyn Expression> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
 }
 
 impl Expression for EmptyExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_empty(self);
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Empty(self)
+    }
+
+    fn span(&self) -> Span {
+        Span::default()
     }
 
     fn dump(&self) -> String {
         String::from("<empty>")
     }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
 }
 
 impl Expression for ConstExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_const(self);
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Const(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.value.span())
     }
 
     fn dump(&self) -> String {
-        String::from(format!("<Const> {} = {}", &self.variable, &self.value.dump()))
+        format!("<Const> {}: {} = {}", &self.variable, &self.type_annotation.dump(), &self.value.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
     }
 }
 
-impl Expression for FunctionExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_func(self);
+impl Expression for Rc<FunctionExpr> {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Func(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
     }
 
     fn dump(&self) -> String {
         let mut parameters_output = String::from("");
 
         for body_expr in &self.body {
-            parameters_output += &*String::from(format!("<Body-Expr> {}\n", body_expr.dump()));
+            parameters_output += &*format!("<Body-Expr> {}\n", body_expr.dump());
         }
 
-        String::from(format!("<Func> {}\n{}", self.name, parameters_output))
+        let parameters: Vec<String> = self.parameters.iter()
+            .map(|parameter| format!("{}: {}", parameter.name, parameter.type_annotation.dump()))
+            .collect();
+
+        format!("<Func> {}({}) -> {}\n{}", self.qualified_name(), parameters.join(", "), self.return_type.dump(), parameters_output)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
     }
 }
 
 impl Expression for StringExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_string(self);
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::String(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 
     fn dump(&self) -> String {
-        String::from(format!("<String> \"{}\"", self.value))
+        format!("<String> \"{}\"", self.value)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
     }
 }
 
 impl Expression for IntExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_int(self);
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Int(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        format!("<Int> \"{}\"", self.value)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for FloatExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Float(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        format!("<Float> \"{:?}\"", self.value)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for BoolExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Bool(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 
     fn dump(&self) -> String {
-        String::from(format!("<Int> \"{}\"", self.value))
+        format!("<Bool> \"{}\"", self.value)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
     }
 }
 
 impl Expression for VariableExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_variable(self);
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Variable(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
     }
 
     fn dump(&self) -> String {
-        String::from(format!("<Variable> \"{}\"", self.name))
+        format!("<Variable> \"{}\"", self.name)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
     }
 }
 
 impl Expression for PrintExpr {
-    fn accept(&self, visitor: &dyn ExpressionVisitor) {
-        visitor.accept_print(self);
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Print(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 
     fn dump(&self) -> String {
         let mut parameters_output = String::from("");
 
         for value_expr in &self.values {
-            parameters_output += &*String::from(format!("{}, ", value_expr.dump()));
+            parameters_output += &*format!("{}, ", value_expr.dump());
+        }
+
+        format!("<Print> {}", parameters_output)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for CallExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Call(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        let mut arguments: Vec<String> = self.arguments.iter().map(|argument| argument.dump()).collect();
+        arguments.extend(self.named_arguments.iter().map(|(name, value)| format!("{}={}", name.lexeme, value.dump())));
+        format!("<Call> {}({})", self.name, arguments.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for BinaryExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Binary(self)
+    }
+
+    fn span(&self) -> Span {
+        self.left.span().to(self.right.span())
+    }
+
+    fn dump(&self) -> String {
+        format!("<Binary> ({} {} {})", self.left.dump(), self.operator.lexeme, self.right.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for UnaryExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Unary(self)
+    }
+
+    fn span(&self) -> Span {
+        self.operator.span.to(self.operand.span())
+    }
+
+    fn dump(&self) -> String {
+        format!("<Unary> ({} {})", self.operator.lexeme, self.operand.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for IfExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::If(self)
+    }
+
+    fn span(&self) -> Span {
+        self.condition.span()
+    }
+
+    fn dump(&self) -> String {
+        let mut body_output = String::from("");
+
+        for body_expr in &self.body {
+            body_output += &*format!("<Body-Expr> {}\n", body_expr.dump());
+        }
+
+        for else_expr in &self.else_body {
+            body_output += &*format!("<Else-Expr> {}\n", else_expr.dump());
+        }
+
+        format!("<If> {}\n{}", self.condition.dump(), body_output)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for WhileExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::While(self)
+    }
+
+    fn span(&self) -> Span {
+        self.condition.span()
+    }
+
+    fn dump(&self) -> String {
+        let mut body_output = String::from("");
+
+        for body_expr in &self.body {
+            body_output += &*format!("<Body-Expr> {}\n", body_expr.dump());
+        }
+
+        format!("<While> {}\n{}", self.condition.dump(), body_output)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for BreakExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Break(self)
+    }
+
+    fn span(&self) -> Span {
+        self.keyword.span
+    }
+
+    fn dump(&self) -> String {
+        String::from("<Break>")
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ContinueExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Continue(self)
+    }
+
+    fn span(&self) -> Span {
+        self.keyword.span
+    }
+
+    fn dump(&self) -> String {
+        String::from("<Continue>")
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ReturnExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Return(self)
+    }
+
+    fn span(&self) -> Span {
+        self.keyword.span.to(self.value.span())
+    }
+
+    fn dump(&self) -> String {
+        format!("<Return> {}", self.value.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for TypeExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Type(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    fn dump(&self) -> String {
+        format!("<Type> {}", self.name)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for VarExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Var(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.value.span())
+    }
+
+    fn dump(&self) -> String {
+        format!("<Var> {}: {} = {}", &self.variable, &self.type_annotation.dump(), &self.value.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for AssignExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Assign(self)
+    }
+
+    fn span(&self) -> Span {
+        self.target.span().to(self.value.span())
+    }
+
+    fn dump(&self) -> String {
+        format!("<Assign> {} {} {}", self.target.dump(), self.operator.lexeme, self.value.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ListExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::List(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        let elements: Vec<String> = self.elements.iter().map(|element| element.dump()).collect();
+        format!("<List> [{}]", elements.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for IndexExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Index(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        format!("<Index> {}[{}]", self.object.dump(), self.index.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for SliceExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Slice(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        format!("<Slice> {}[{}:{}]", self.object.dump(), self.start.dump(), self.end.dump())
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ForExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::For(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.iterable.span())
+    }
+
+    fn dump(&self) -> String {
+        let mut body_output = String::from("");
+
+        for body_expr in &self.body {
+            body_output += &*format!("<Body-Expr> {}\n", body_expr.dump());
         }
 
-        String::from(format!("<Print> {}", parameters_output))
+        let variables = match &self.value_variable {
+            Some(value_variable) => format!("{}, {}", self.variable, value_variable.lexeme),
+            None => self.variable.to_string()
+        };
+
+        format!("<For> {} in {}\n{}", variables, self.iterable.dump(), body_output)
     }
-}
\ No newline at end of file
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for MapExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Map(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        let entries: Vec<String> = self.entries.iter()
+            .map(|(key, value)| format!("{}: {}", key.dump(), value.dump()))
+            .collect();
+
+        format!("<Map> {{{}}}", entries.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for Rc<StructExpr> {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Struct(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    fn dump(&self) -> String {
+        let fields: Vec<String> = self.fields.iter()
+            .map(|field| format!("{}: {}", field.name, field.type_annotation.dump()))
+            .collect();
+
+        format!("<Struct> {}({})", self.name, fields.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for GetExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Get(self)
+    }
+
+    fn span(&self) -> Span {
+        self.object.span().to(self.token.span)
+    }
+
+    fn dump(&self) -> String {
+        format!("<Get> {}.{}", self.object.dump(), self.name)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for Rc<EnumExpr> {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Enum(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    fn dump(&self) -> String {
+        let variants: Vec<String> = self.variants.iter()
+            .map(|variant| {
+                let fields: Vec<String> = variant.fields.iter()
+                    .map(|field| format!("{}: {}", field.name, field.type_annotation.dump()))
+                    .collect();
+                format!("{}({})", variant.name, fields.join(", "))
+            })
+            .collect();
+
+        format!("<Enum> {} {{{}}}", self.name, variants.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for MethodCallExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::MethodCall(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|argument| argument.dump()).collect();
+        format!("<MethodCall> {}.{}({})", self.object.dump(), self.name, arguments.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for MatchExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Match(self)
+    }
+
+    fn span(&self) -> Span {
+        self.keyword.span.to(self.subject.span())
+    }
+
+    fn dump(&self) -> String {
+        let mut arms_output = String::from("");
+
+        for arm in &self.arms {
+            arms_output += &*format!("<Arm> {} if {}\n", arm.pattern.dump(), arm.guard.dump());
+
+            for body_expr in &arm.body {
+                arms_output += &*format!("<Body-Expr> {}\n", body_expr.dump());
+            }
+        }
+
+        format!("<Match> {}\n{}", self.subject.dump(), arms_output)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ImplExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Impl(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    fn dump(&self) -> String {
+        let mut functions_output = String::from("");
+
+        for function in &self.functions {
+            functions_output += &*format!("<Body-Expr> {}\n", function.dump());
+        }
+
+        format!("<Impl> {}\n{}", self.type_name, functions_output)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for LambdaExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Lambda(self)
+    }
+
+    fn span(&self) -> Span {
+        match self.function.body.last() {
+            Some(last) => self.token.span.to(last.span()),
+            None => self.token.span
+        }
+    }
+
+    fn dump(&self) -> String {
+        self.function.dump()
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ApplyExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Apply(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn dump(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|argument| argument.dump()).collect();
+        format!("<Apply> {}({})", self.callee.dump(), arguments.join(", "))
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+impl Expression for ImportExpr {
+    fn kind(&self) -> ExpressionKind<'_> {
+        ExpressionKind::Import(self)
+    }
+
+    fn span(&self) -> Span {
+        match self.names.last() {
+            Some(last) => self.module.span.to(last.span),
+            None => self.module.span
+        }
+    }
+
+    fn dump(&self) -> String {
+        if self.names.is_empty() {
+            return format!("<Import> {}", self.module.lexeme);
+        }
+
+        let names: Vec<&str> = self.names.iter().map(|name| name.lexeme.as_str()).collect();
+        format!("<Import> {} from {}", names.join(", "), self.module.lexeme)
+    }
+
+    fn box_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
@@ -1,35 +1,78 @@
-use chrono::Local;
-use log::info;
+use std::thread;
+use log::error;
 
-use crate::parser::Parser;
-use crate::reporter::CodeReporter;
-use crate::runtime::{InterpreterRuntime, RuntimeEngine};
-use crate::scanner::Scanner;
+use crate::checker::TypeChecker;
+use crate::codes::Code;
+use crate::modules;
+use crate::reporter::{CodeReporter, Diagnostic, ErrorFormat};
+use crate::runtime::InterpreterRuntime;
+use crate::scanner::Span;
 
-pub fn run(file_name: String, source: String) {
-    let reporter = CodeReporter::new();
-    let mut scanner = Scanner::new(reporter);
+/// Native stack of the thread a program runs on. Every call of the program nests
+/// a few dozen native frames, the default stack would overflow long before
+/// `MAX_CALL_DEPTH` calls. Only the part which is used gets allocated.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
-    let start_scanning = Local::now().time();
-    let tokens = scanner.scan_string(file_name, source);
-    let end_scanning = Local::now().time();
+/// Runs a program and returns whether it ran without errors. Nothing is
+/// executed if scanning, parsing, resolving imports or type checking failed.
+pub fn run(file_name: String, source: String, error_format: ErrorFormat) -> bool {
+    thread::Builder::new()
+        .name(String::from("interpreter"))
+        .stack_size(STACK_SIZE)
+        .spawn(move || run_on_current_thread(file_name, source, error_format))
+        .expect("Could not start the interpreter thread")
+        .join()
+        .unwrap_or(false)
+}
 
-    info!("Scanning took {} ms", (end_scanning - start_scanning).num_milliseconds());
+fn run_on_current_thread(file_name: String, source: String, error_format: ErrorFormat) -> bool {
+    let reporter = CodeReporter::new(error_format);
 
-    for token in &tokens {
-        info!("Token: {:?}", token);
+    let program = match modules::load(file_name.to_string(), source, reporter.clone()) {
+        Some(program) => program,
+        None => {
+            error!("The program has errors and is not executed");
+            reporter.report_summary();
+            return false;
+        }
+    };
+
+    let mut checker = TypeChecker::new(reporter.clone());
+    if !checker.check_program(&program) {
+        error!("Type check failed, the program is not executed");
+        reporter.report_summary();
+        return false;
+    }
+
+    let mut runtime = InterpreterRuntime::new();
+    if let Err(runtime_error) = runtime.execute_program(&program) {
+        // errors which cannot be located belong to the program as a whole
+        let (file_name, span) = runtime_error.location.clone().unwrap_or((file_name, Span::default()));
+        let diagnostic = runtime_error.trace_lines().iter()
+            .fold(Diagnostic::error(Code::RuntimeError, &file_name, span, &runtime_error.message), |diagnostic, line| diagnostic.with_note(line));
+        reporter.report(diagnostic);
     }
 
-    let mut parser = Parser::new(tokens);
+    reporter.report_summary();
+    !reporter.has_error()
+}
 
-    let start_parser = Local::now().time();
-    let ast = parser.parse_ast();
-    let end_parser = Local::now().time();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    info!("Parsing took {} ms", (end_parser - start_parser).num_milliseconds());
+    fn recursion(depth: usize) -> String {
+        format!("fun f(n)\n    if n == 0\n        return 0\n    return f(n - 1) + 1\nconst depth: int = f({})\n", depth)
+    }
 
-    ast.dump();
+    #[test]
+    fn deep_recursion_succeeds() {
+        assert!(run(String::from("main.hunt"), recursion(1000), ErrorFormat::Muted));
+        assert!(run(String::from("main.hunt"), recursion(4000), ErrorFormat::Muted));
+    }
 
-    let runtime = InterpreterRuntime::new();
-    runtime.execute_ast(&ast);
-}
\ No newline at end of file
+    #[test]
+    fn unbounded_recursion_fails() {
+        assert!(!run(String::from("main.hunt"), recursion(100_000), ErrorFormat::Muted));
+    }
+}
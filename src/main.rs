@@ -1,16 +1,28 @@
+// The token types mirror the keywords they represent and functions prefer explicit returns
+#![allow(clippy::upper_case_acronyms, clippy::needless_return)]
+
 mod scanner;
 mod interpreter;
 mod reporter;
 mod parser;
 mod runtime;
 mod expressions;
+mod checker;
+mod value;
+mod environment;
+mod builtins;
+mod modules;
+mod codes;
 
-use std::{env, fs};
+use std::{env, fs, process};
+use std::io::IsTerminal;
 use chrono::Local;
 use fern::colors::{Color, ColoredLevelConfig};
-use log::info;
+use log::{error, info};
 
+use crate::codes::Code;
 use crate::interpreter::run;
+use crate::reporter::ErrorFormat;
 
 fn setup_logger() -> Result<(), fern::InitError> {
     let mut colors = ColoredLevelConfig::new()
@@ -40,15 +52,61 @@ fn setup_logger() -> Result<(), fern::InitError> {
 fn main() {
     let result = setup_logger();
 
-    match result {
-        Err(error) => panic!("Logger setup failed: {}", error),
-        _ => {}
+    if let Err(error) = result {
+        panic!("Logger setup failed: {}", error)
     }
 
     let args: Vec<String> = env::args().collect();
     info!("Compiler arguments: {:?}", args);
 
-    let file_name: String = args[1].clone();
+    // `colored` looks at stdout to decide, but diagnostics are written to stderr
+    let auto_color = || colored::control::set_override(std::io::stderr().is_terminal());
+    auto_color();
+
+    let mut file_name: Option<String> = None;
+    let mut error_format = ErrorFormat::Human;
+    let mut arguments = args[1..].iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--color=always" => colored::control::set_override(true),
+            "--color=never" => colored::control::set_override(false),
+            "--color=auto" => auto_color(),
+            "--error-format=human" => error_format = ErrorFormat::Human,
+            "--error-format=json" => error_format = ErrorFormat::Json,
+            "--explain" => process::exit(explain(arguments.next())),
+            option if option.starts_with("--") => {
+                error!("Unknown option `{}`", option);
+                process::exit(2);
+            },
+            _ => file_name = Some(argument.clone()),
+        }
+    }
+
+    let file_name = match file_name {
+        Some(file_name) => file_name,
+        None => {
+            error!("Usage: rust_hunter_language [--color=auto|always|never] [--error-format=human|json] <file> | --explain <code>");
+            process::exit(2);
+        }
+    };
+
     let file_content = fs::read_to_string(&file_name).expect("Something went wrong during reading");
-    run(file_name, file_content);
+    if !run(file_name, file_content, error_format) {
+        process::exit(1);
+    }
+}
+
+/// Prints the long form description of a diagnostic code and returns the exit status
+fn explain(code: Option<&String>) -> i32 {
+    match code.and_then(|code| Code::from_name(code)) {
+        Some(code) => {
+            println!("{}", code.explanation());
+            0
+        },
+        None => {
+            let codes: Vec<&str> = Code::ALL.iter().map(|code| code.name()).collect();
+            error!("Unknown code `{}`, known codes are {}", code.map(String::as_str).unwrap_or(""), codes.join(", "));
+            2
+        }
+    }
 }
@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::Path;
+use chrono::Local;
+use log::info;
+
+use crate::expressions::ExpressionKind;
+use crate::parser::{Ast, Parser};
+use crate::codes::Code;
+use crate::reporter::CodeReporter;
+use crate::scanner::{Scanner, Token};
+
+/// Parsed source file of a program
+pub struct Module {
+    /// Canonical path, the same file imported through different paths is loaded once
+    pub key: String,
+    pub file_name: String,
+    pub ast: Ast,
+}
+
+/// The main file together with everything it imports. Every module comes
+/// after the modules it imports, so the main file is the last one.
+pub struct Program {
+    pub modules: Vec<Module>,
+}
+
+/// File an `import name` refers to, relative to the directory of the importing file
+pub fn module_path(importing_file: &str, name: &str) -> String {
+    let directory = Path::new(importing_file).parent().unwrap_or(Path::new(""));
+    directory.join(format!("{}.hunt", name)).to_string_lossy().to_string()
+}
+
+/// Key of the module `name` imported by `importing_file`, `None` if there is no such file
+pub fn module_key(importing_file: &str, name: &str) -> Option<String> {
+    canonical_path(&module_path(importing_file, name))
+}
+
+fn canonical_path(file_name: &str) -> Option<String> {
+    fs::canonicalize(file_name).ok().map(|path| path.to_string_lossy().to_string())
+}
+
+/// Loads the main file and all modules it imports, returns `None` if a file has errors
+/// or an import could not be resolved
+pub fn load(file_name: String, source: String, reporter: CodeReporter) -> Option<Program> {
+    let mut loader = ModuleLoader { reporter, modules: vec![], loading: vec![] };
+    loader.load_module(file_name, source);
+
+    if loader.reporter.has_error() {
+        return None;
+    }
+
+    Some(Program { modules: loader.modules })
+}
+
+struct ModuleLoader {
+    reporter: CodeReporter,
+    modules: Vec<Module>,
+    /// Modules whose imports are being loaded as `(key, file name)`, outermost first
+    loading: Vec<(String, String)>,
+}
+
+impl ModuleLoader {
+    fn load_module(&mut self, file_name: String, source: String) {
+        let key = canonical_path(&file_name).unwrap_or(file_name.to_string());
+        let ast = self.parse(file_name.to_string(), source);
+
+        self.loading.push((key.to_string(), file_name.to_string()));
+        for expression in &ast.expressions {
+            if let ExpressionKind::Import(import) = expression.kind() {
+                self.load_import(&file_name, &import.module);
+            }
+        }
+        self.loading.pop();
+
+        self.modules.push(Module { key, file_name, ast });
+    }
+
+    fn load_import(&mut self, importing_file: &str, module: &Token) {
+        let path = module_path(importing_file, &module.lexeme);
+        let key = match canonical_path(&path) {
+            Some(key) => key,
+            None => return self.report(module, Code::UnresolvedImport, format!("Cannot find module `{}` at `{}`", module.lexeme, path))
+        };
+
+        if let Some(position) = self.loading.iter().position(|(loading, _)| *loading == key) {
+            let mut chain: Vec<&str> = self.loading[position..].iter().map(|(_, file_name)| file_name.as_str()).collect();
+            chain.push(&path);
+
+            let message = format!("Import cycle: {}", chain.join(" -> "));
+            return self.report(module, Code::ImportCycle, message);
+        }
+
+        if self.modules.iter().any(|loaded| loaded.key == key) {
+            return;
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(source) => self.load_module(path, source),
+            Err(error) => self.report(module, Code::UnresolvedImport, format!("Cannot read module `{}`: {}", module.lexeme, error)),
+        }
+    }
+
+    fn parse(&mut self, file_name: String, source: String) -> Ast {
+        let mut scanner = Scanner::new(self.reporter.clone());
+
+        let start_scanning = Local::now().time();
+        let tokens = scanner.scan_string(file_name, source);
+        let end_scanning = Local::now().time();
+
+        info!("Scanning took {} ms", (end_scanning - start_scanning).num_milliseconds());
+
+        for token in &tokens {
+            info!("Token: {:?}", token);
+        }
+
+        let mut parser = Parser::new(tokens, self.reporter.clone());
+
+        let start_parser = Local::now().time();
+        let ast = parser.parse_ast();
+        let end_parser = Local::now().time();
+
+        info!("Parsing took {} ms", (end_parser - start_parser).num_milliseconds());
+
+        ast.dump();
+        ast
+    }
+
+    fn report(&mut self, token: &Token, code: Code, message: String) {
+        self.reporter.report_error(code, &token.file_name, token.span, &message);
+    }
+}
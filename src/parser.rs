@@ -1,8 +1,10 @@
 use std::borrow::Borrow;
+use std::rc::Rc;
 use log::info;
-use crate::expressions::{ConstExpr, EmptyExpr, Expression, FunctionExpr, IntExpr, PrintExpr, StringExpr, VariableExpr};
-use crate::reporter::CodeReporter;
-use crate::scanner::{Token, TokenType};
+use crate::expressions::{ApplyExpr, AssignExpr, BinaryExpr, BoolExpr, BreakExpr, CallExpr, ConstExpr, ContinueExpr, EmptyExpr, EnumExpr, Expression, ExpressionKind, Field, FloatExpr, ForExpr, FunctionExpr, GetExpr, IfExpr, ImplExpr, ImportExpr, IndexExpr, IntExpr, LambdaExpr, ListExpr, MapExpr, MatchArm, MatchExpr, MethodCallExpr, Parameter, Pattern, PrintExpr, ReturnExpr, SliceExpr, StringExpr, StructExpr, TypeExpr, UnaryExpr, VarExpr, VariableExpr, Variant, WhileExpr};
+use crate::codes::Code;
+use crate::reporter::{CodeReporter, ErrorFormat};
+use crate::scanner::{Scanner, Span, Token, TokenType};
 
 pub struct Ast {
     pub expressions: Vec<Box<dyn Expression>>
@@ -16,198 +18,1238 @@ impl Ast {
     }
 }
 
+/// Problem found while parsing, located at the span of the token which caused it
+struct ParseError {
+    code: Code,
+    file_name: String,
+    span: Span,
+    message: String,
+    /// Follows an invalid token the scanner already reported, only recovery is left to do
+    reported: bool,
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+const LOWEST_PRECEDENCE: u8 = 1;
+const NOT_PRECEDENCE: u8 = 3;
+const NEGATE_PRECEDENCE: u8 = 8;
+
+/// Binding strength of a binary operator, 0 if the token is no binary operator.
+/// `not` sits between `and` and the comparisons, so `not a == b` negates the comparison,
+/// while `!` binds as tight as the unary minus, so `!a == b` compares the negation.
+fn binary_precedence(token_type: TokenType) -> u8 {
+    match token_type {
+        TokenType::OR => 1,
+        TokenType::AND => 2,
+        TokenType::EqualEqual | TokenType::BangEqual => 4,
+        TokenType::LESS | TokenType::LessEqual | TokenType::GREATER | TokenType::GreaterEqual => 5,
+        TokenType::PLUS | TokenType::MINUS => 6,
+        TokenType::STAR | TokenType::SLASH | TokenType::PERCENT => 7,
+        _ => 0
+    }
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
+    reporter: CodeReporter,
     current_token_index: usize,
     current_level: i32,
+    loop_depth: usize,
+    function_depth: usize,
 }
 
 impl Parser {
 
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens: tokens, current_token_index: 0, current_level: 0 }
+    pub fn new(tokens: Vec<Token>, reporter: CodeReporter) -> Self {
+        Parser { tokens, reporter, current_token_index: 0, current_level: 0, loop_depth: 0, function_depth: 0 }
     }
 
     pub fn parse_ast(&mut self) -> Ast {
+        let expressions = self.parse_lines(|parser, token| parser.parse_expr(&token));
+        Ast { expressions }
+    }
 
-        let mut reporter = CodeReporter::new();
-        let mut ast = Ast{ expressions: vec![] };
+    fn parse_expr(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        if token.token_type == TokenType::FUNCTION && self.peek().token_type != TokenType::LeftParen {
+            Ok(Box::new(self.parse_function(None)?))
+        } else if token.token_type == TokenType::IMPL {
+            self.parse_impl()
+        } else if token.token_type == TokenType::IMPORT || token.token_type == TokenType::FROM {
+            self.parse_import(token.token_type)
+        } else if token.token_type == TokenType::STRUCT {
+            self.parse_struct()
+        } else if token.token_type == TokenType::ENUM {
+            self.parse_enum()
+        } else if token.token_type == TokenType::CONST || token.token_type == TokenType::VAR {
+            self.parse_declaration(token.token_type)
+        } else if token.token_type == TokenType::PRINT {
+            self.parse_print(token)
+        } else if token.token_type == TokenType::IF {
+            self.parse_if()
+        } else if token.token_type == TokenType::WHILE {
+            self.parse_while()
+        } else if token.token_type == TokenType::FOR {
+            self.parse_for()
+        } else if token.token_type == TokenType::BREAK {
+            self.parse_break(token)
+        } else if token.token_type == TokenType::CONTINUE {
+            self.parse_continue(token)
+        } else if token.token_type == TokenType::RETURN {
+            self.parse_return()
+        } else {
+            self.parse_assignment(token)
+        }
+    }
 
-        while !self.is_at_end() {
-            let token = self.advance();
-            let expr = self.parse_expr(&token);
+    /// Precedence climbing over the binary operators: operands are parsed first and
+    /// then every following operator binding at least as strong as `min_precedence`
+    /// takes the expression parsed so far as its left side.
+    fn parse_operation(&mut self, token: &Token, min_precedence: u8) -> ParseResult<Box<dyn Expression>> {
+        let mut left = self.parse_unary(token)?;
 
-            if token.token_type == TokenType::LineBreak {
-                continue
+        while !self.is_at_end() && !self.ended_block() {
+            let precedence = binary_precedence(self.peek().token_type);
+            if precedence == 0 || precedence < min_precedence {
+                break;
             }
 
-            match expr {
-                Ok(..) => ast.expressions.push(expr.unwrap()),
-                Err(..) => {
-                    reporter.report_error(
-                        &token.file_name,
-                        token.line,
-                        &String::from(expr.err().unwrap())
-                    );
-                }
+            let operator = self.advance();
+            let right_token = self.advance();
+            let right = self.parse_operation(&right_token, precedence + 1)?;
+
+            left = Box::new(BinaryExpr { left, operator, right });
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let operand_precedence = match token.token_type {
+            TokenType::MINUS | TokenType::BANG => NEGATE_PRECEDENCE,
+            TokenType::NOT => NOT_PRECEDENCE,
+            _ => return self.parse_postfix(token)
+        };
+
+        let operand_token = self.advance();
+        let operand = self.parse_operation(&operand_token, operand_precedence)?;
+
+        Ok(Box::new(UnaryExpr { operator: token.clone(), operand }))
+    }
+
+    /// Parses a primary expression followed by any number of `[index]`, `[start:end]`, `.field` or `(arguments)`
+    fn parse_postfix(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let mut expr = self.parse_primary(token)?;
+
+        while !self.is_at_end() && !self.ended_block() {
+            match self.peek().token_type {
+                TokenType::LeftBracket => expr = self.parse_index(expr)?,
+                TokenType::DOT => expr = self.parse_get(expr)?,
+                TokenType::LeftParen => expr = self.parse_apply(expr)?,
+                _ => break
             }
         }
 
-        ast
+        Ok(expr)
     }
 
-    fn parse_expr(&mut self, token: &Token) -> Result<Box<dyn Expression>, &str> {
-        if token.token_type == TokenType::FUNCTION {
-            self.parse_function()
-        } else if token.token_type == TokenType::CONST {
-            self.parse_const()
-        } else if token.token_type == TokenType::PRINT {
-            self.parse_print()
-        } else if token.token_type == TokenType::STRING {
+    fn parse_get(&mut self, object: Box<dyn Expression>) -> ParseResult<Box<dyn Expression>> {
+        // dot
+        self.advance();
+
+        let token = self.advance();
+        if token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&token, "Missing field name after `.`"));
+        }
+
+        if self.peek().token_type != TokenType::LeftParen {
+            return Ok(Box::new(GetExpr { object, name: token.lexeme.to_string(), token }));
+        }
+
+        let mut named_arguments: Vec<(Token, Box<dyn Expression>)> = vec![];
+        let arguments = self.parse_arguments(&mut named_arguments)?;
+        if let Some((name, _)) = named_arguments.first() {
+            return Err(self.syntax_error(name, "Named arguments are only supported when constructing a struct"));
+        }
+
+        let span = object.span().to(self.previous().span);
+        Ok(Box::new(MethodCallExpr { object, name: token.lexeme.to_string(), token, arguments, span }))
+    }
+
+    fn parse_apply(&mut self, callee: Box<dyn Expression>) -> ParseResult<Box<dyn Expression>> {
+        let token = self.peek();
+
+        let mut named_arguments: Vec<(Token, Box<dyn Expression>)> = vec![];
+        let arguments = self.parse_arguments(&mut named_arguments)?;
+        if let Some((name, _)) = named_arguments.first() {
+            return Err(self.syntax_error(name, "Named arguments are only supported when constructing a struct"));
+        }
+
+        let span = callee.span().to(self.previous().span);
+        Ok(Box::new(ApplyExpr { callee, token, arguments, span }))
+    }
+
+    fn parse_index(&mut self, object: Box<dyn Expression>) -> ParseResult<Box<dyn Expression>> {
+        let token = self.advance();
+
+        let start = self.parse_slice_bound()?;
+        if self.peek().token_type != TokenType::COLON {
+            if matches!(start.kind(), ExpressionKind::Empty(..)) {
+                return Err(self.syntax_error(&token, "Missing index between brackets"));
+            }
+
+            let right_bracket_token = self.advance();
+            if right_bracket_token.token_type != TokenType::RightBracket {
+                return Err(self.syntax_error(&right_bracket_token, "Missing `]` after index"));
+            }
+
+            let span = object.span().to(self.previous().span);
+            return Ok(Box::new(IndexExpr { object, index: start, token, span }));
+        }
+
+        // colon
+        self.advance();
+        let end = self.parse_slice_bound()?;
+
+        let right_bracket_token = self.advance();
+        if right_bracket_token.token_type != TokenType::RightBracket {
+            return Err(self.syntax_error(&right_bracket_token, "Missing `]` after slice"));
+        }
+
+        let span = object.span().to(self.previous().span);
+        Ok(Box::new(SliceExpr { object, start, end, token, span }))
+    }
+
+    /// An omitted bound of a slice becomes an `EmptyExpr`
+    fn parse_slice_bound(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let next_token_type = self.peek().token_type;
+        if next_token_type == TokenType::COLON || next_token_type == TokenType::RightBracket {
+            return Ok(Box::new(EmptyExpr{}));
+        }
+
+        let token = self.advance();
+        self.parse_operation(&token, LOWEST_PRECEDENCE)
+    }
+
+    /// Parses the elements of a list literal after its `[`, which may span several lines
+    fn parse_list(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let mut elements: Vec<Box<dyn Expression>> = vec![];
+
+        loop {
+            self.skip_line_breaks();
+            if self.is_at_end() {
+                return Err(self.syntax_error(token, "Missing `]` after list elements"));
+            }
+
+            let element_token = self.advance();
+            if element_token.token_type == TokenType::RightBracket {
+                break;
+            }
+
+            elements.push(self.parse_operation(&element_token, LOWEST_PRECEDENCE)?);
+            self.skip_line_breaks();
+
+            let separator_token = self.advance();
+            if separator_token.token_type == TokenType::RightBracket {
+                break;
+            } else if separator_token.token_type != TokenType::COMMA {
+                return Err(self.syntax_error(&separator_token, "Comma missing after list element"));
+            }
+        }
+
+        Ok(Box::new(ListExpr { elements, span: token.span.to(self.previous().span) }))
+    }
+
+    /// Parses the entries of a map literal after its `{`, which may span several lines
+    fn parse_map(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let mut entries: Vec<(Box<dyn Expression>, Box<dyn Expression>)> = vec![];
+
+        loop {
+            self.skip_line_breaks();
+            if self.is_at_end() {
+                return Err(self.syntax_error(token, "Missing `}` after map entries"));
+            }
+
+            let key_token = self.advance();
+            if key_token.token_type == TokenType::RightBrace {
+                break;
+            }
+
+            let key = self.parse_operation(&key_token, LOWEST_PRECEDENCE)?;
+            self.skip_line_breaks();
+
+            let colon_token = self.advance();
+            if colon_token.token_type != TokenType::COLON {
+                return Err(self.syntax_error(&colon_token, "Missing `:` after map key"));
+            }
+
+            self.skip_line_breaks();
+            let value_token = self.advance();
+            let value = self.parse_operation(&value_token, LOWEST_PRECEDENCE)?;
+            entries.push((key, value));
+            self.skip_line_breaks();
+
+            let separator_token = self.advance();
+            if separator_token.token_type == TokenType::RightBrace {
+                break;
+            } else if separator_token.token_type != TokenType::COMMA {
+                return Err(self.syntax_error(&separator_token, "Comma missing after map entry"));
+            }
+        }
+
+        Ok(Box::new(MapExpr { entries, token: token.clone(), span: token.span.to(self.previous().span) }))
+    }
+
+    fn parse_primary(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        if token.token_type == TokenType::STRING {
             self.parse_string(token)
         } else if token.token_type == TokenType::INT {
             self.parse_int(token)
+        } else if token.token_type == TokenType::FLOAT {
+            self.parse_float(token)
+        } else if token.token_type == TokenType::TRUE || token.token_type == TokenType::FALSE {
+            Ok(Box::new(BoolExpr{ value: token.token_type == TokenType::TRUE, span: token.span }))
         } else if token.token_type == TokenType::IDENTIFIER {
             self.parse_variable(token)
+        } else if token.token_type == TokenType::LeftParen {
+            self.parse_group()
+        } else if token.token_type == TokenType::LeftBracket {
+            self.parse_list(token)
+        } else if token.token_type == TokenType::LeftBrace {
+            self.parse_map(token)
+        } else if token.token_type == TokenType::MATCH {
+            self.parse_match(token)
+        } else if token.token_type == TokenType::FUNCTION {
+            self.parse_lambda(token)
         } else {
-            Err("Could not parse an expression")
+            Err(self.syntax_error(token, "Could not parse an expression"))
         }
     }
 
-    fn parse_print(&mut self) -> Result<Box<dyn Expression>, &str> {
+    fn parse_group(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let token = self.advance();
+        let expr = self.parse_operation(&token, LOWEST_PRECEDENCE)?;
+
+        let right_paren_token = self.advance();
+        if right_paren_token.token_type != TokenType::RightParen {
+            return Err(self.syntax_error(&right_paren_token, "Missing right paren after grouped expression"));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_print(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
         let left_paren_token = self.advance();
         if left_paren_token.token_type != TokenType::LeftParen {
-            return Err("Missing left paren after function name");
+            return Err(self.syntax_error(&left_paren_token, "Missing left paren after function name"));
         }
 
         let mut values: Vec<Box<dyn Expression>> = vec![];
         let mut current_token = self.advance();
         while current_token.token_type != TokenType::RightParen && !self.is_at_end() {
-            let expr_result = self.parse_expr(&current_token);
-            if expr_result.is_ok() {
-                values.push(expr_result.unwrap());
-                current_token = self.advance();
+            values.push(self.parse_expr(&current_token)?);
+            current_token = self.advance();
 
-                if current_token.token_type != TokenType::COMMA && current_token.token_type != TokenType::RightParen {
-                    return Err("Comma missing after parameter")
-                } else if current_token.token_type == TokenType::COMMA {
-                    current_token = self.advance();
-                }
-            } else {
-                break;
+            if current_token.token_type != TokenType::COMMA && current_token.token_type != TokenType::RightParen {
+                return Err(self.syntax_error(&current_token, "Comma missing after parameter"))
+            } else if current_token.token_type == TokenType::COMMA {
+                current_token = self.advance();
             }
+        }
 
+        if self.previous().token_type != TokenType::RightParen {
+            return Err(self.syntax_error(&left_paren_token, "Missing right paren after function call"));
         }
 
-        Ok(Box::new(PrintExpr { values: values }))
+        Ok(Box::new(PrintExpr { values, span: token.span.to(self.previous().span) }))
     }
 
-    fn parse_const(&mut self) -> Result<Box<dyn Expression>, &str> {
+    /// Parses `const` and `var` declarations, `name: type = value` after the keyword
+    fn parse_declaration(&mut self, keyword: TokenType) -> ParseResult<Box<dyn Expression>> {
         let identifier_token = self.advance();
         if identifier_token.token_type != TokenType::IDENTIFIER {
-            return Err("Missing identifier after const keyword");
+            return Err(self.syntax_error(&identifier_token, if keyword == TokenType::CONST {
+                "Missing identifier after const keyword"
+            } else {
+                "Missing identifier after var keyword"
+            }));
         }
 
+        let type_annotation = self.parse_type_annotation()?;
+
         let equal_token = self.advance();
         if equal_token.token_type != TokenType::EQUAL {
-            return Err("Missing equal after identifier");
+            return Err(self.syntax_error(&equal_token, "Missing equal after identifier"));
         }
 
         let token = self.advance();
-        let value = self.parse_expr(&token);
+        let value = self.parse_operation(&token, LOWEST_PRECEDENCE)?;
+        let variable = identifier_token.lexeme.to_string();
 
-        if value.is_err() {
-            return Err(value.err().unwrap())
+        if keyword == TokenType::CONST {
+            Ok(Box::new(ConstExpr{ variable, token: identifier_token, type_annotation, value }))
+        } else {
+            Ok(Box::new(VarExpr{ variable, token: identifier_token, type_annotation, value }))
         }
+    }
+
+    /// An expression followed by `=`, `+=` or `-=` becomes an assignment to it
+    fn parse_assignment(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let target = self.parse_operation(token, LOWEST_PRECEDENCE)?;
 
-        Ok(Box::new(ConstExpr{ variable: identifier_token.lexeme, value: value.unwrap() }))
+        let operator_type = self.peek().token_type;
+        if operator_type != TokenType::EQUAL && operator_type != TokenType::PlusEqual && operator_type != TokenType::MinusEqual {
+            return Ok(target);
+        }
+
+        let operator = self.advance();
+        if !matches!(target.kind(), ExpressionKind::Variable(..) | ExpressionKind::Index(..) | ExpressionKind::Get(..)) {
+            return Err(self.syntax_error(&operator, "Invalid assignment target"));
+        }
+
+        let value_token = self.advance();
+        let value = self.parse_operation(&value_token, LOWEST_PRECEDENCE)?;
+
+        Ok(Box::new(AssignExpr { target, operator, value }))
+    }
+
+    /// Parses an optional `: type` annotation, an `EmptyExpr` stands for no annotation
+    fn parse_type_annotation(&mut self) -> ParseResult<Box<dyn Expression>> {
+        if self.peek().token_type != TokenType::COLON {
+            return Ok(Box::new(EmptyExpr{}));
+        }
+
+        // colon
+        self.advance();
+        self.parse_type()
     }
 
-    fn parse_string(&mut self, token: &Token) -> Result<Box<dyn Expression>, &str> {
-        Ok(Box::new(StringExpr{ value: token.lexeme.to_string() }))
+    fn parse_type(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let type_token = self.advance();
+        if type_token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&type_token, "Missing type name"));
+        }
+
+        Ok(Box::new(TypeExpr { name: type_token.lexeme.to_string(), token: type_token }))
     }
 
-    fn parse_int(&mut self, token: &Token) -> Result<Box<dyn Expression>, &str> {
-        Ok(Box::new(IntExpr{ value: token.lexeme.to_string().parse().unwrap() }))
+    /// Desugars the interpolations of a string literal: `"a {b} c"` becomes `"a " + b + " c"`
+    fn parse_string(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let mut parts: Vec<Box<dyn Expression>> = vec![];
+        let mut literal = String::new();
+        let mut characters = token.lexeme.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            match character {
+                '{' | '}' if characters.peek() == Some(&character) => {
+                    characters.next();
+                    literal.push(character);
+                },
+                '}' => return Err(self.syntax_error(token, "Unmatched `}` in string, use `}}` for a literal brace")),
+                '{' => {
+                    let mut source = String::new();
+                    loop {
+                        match characters.next() {
+                            Some('}') => break,
+                            Some(character) => source.push(character),
+                            None => return Err(self.syntax_error(token, "Unclosed `{` in string interpolation")),
+                        }
+                    }
+
+                    // the leading string makes sure `+` concatenates
+                    if !literal.is_empty() || parts.is_empty() {
+                        parts.push(Box::new(StringExpr{ value: std::mem::take(&mut literal), span: token.span }));
+                    }
+
+                    parts.push(self.parse_interpolation(token, source)?);
+                },
+                _ => literal.push(character)
+            }
+        }
+
+        if !literal.is_empty() || parts.is_empty() {
+            parts.push(Box::new(StringExpr{ value: literal, span: token.span }));
+        }
+
+        let mut parts = parts.into_iter();
+        let mut expr = parts.next().unwrap();
+
+        for part in parts {
+            let operator = Token::new(TokenType::PLUS, String::from("+"), token.file_name.to_string(), token.span);
+            expr = Box::new(BinaryExpr { left: expr, operator, right: part });
+        }
+
+        Ok(expr)
     }
 
-    fn parse_variable(&mut self, token: &Token) -> Result<Box<dyn Expression>, &str> {
-        Ok(Box::new(VariableExpr{ name: token.lexeme.to_string() }))
+    fn parse_interpolation(&mut self, token: &Token, source: String) -> ParseResult<Box<dyn Expression>> {
+        // positions inside the interpolation are meaningless in the file, a broken
+        // token is reported as a parse error at the whole string literal instead
+        let embedded_reporter = CodeReporter::new(ErrorFormat::Muted);
+        let mut scanner = Scanner::new(embedded_reporter.clone());
+        let tokens: Vec<Token> = scanner.scan_string(token.file_name.to_string(), source)
+            .into_iter()
+            .filter(|embedded_token| embedded_token.token_type != TokenType::SpaceLevel)
+            .map(|embedded_token| Token { span: token.span, ..embedded_token })
+            .collect();
+
+        if embedded_reporter.has_error() {
+            return Err(self.syntax_error(token, "Invalid string in string interpolation"));
+        }
+
+        let mut parser = Parser::new(tokens, self.reporter.clone());
+        if parser.is_at_end() {
+            return Err(self.syntax_error(token, "Empty expression in string interpolation"));
+        }
+
+        let first_token = parser.advance();
+        let expr = parser.parse_operation(&first_token, LOWEST_PRECEDENCE)?;
+
+        if !parser.is_at_end() {
+            return Err(self.syntax_error(token, "Unexpected token in string interpolation"));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_int(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        match token.lexeme.parse() {
+            Ok(value) => Ok(Box::new(IntExpr{ value, span: token.span })),
+            Err(..) => Err(self.syntax_error(token, "Integer literal is too large"))
+        }
+    }
+
+    fn parse_float(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        match token.lexeme.parse() {
+            Ok(value) => Ok(Box::new(FloatExpr{ value, span: token.span })),
+            Err(..) => Err(self.syntax_error(token, "Invalid float literal"))
+        }
+    }
+
+    fn parse_variable(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        if !self.is_at_end() && self.peek().token_type == TokenType::LeftParen {
+            return self.parse_call(token);
+        }
+
+        Ok(Box::new(VariableExpr{ name: token.lexeme.to_string(), token: token.clone() }))
+    }
+
+    fn parse_call(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let mut named_arguments: Vec<(Token, Box<dyn Expression>)> = vec![];
+        let arguments = self.parse_arguments(&mut named_arguments)?;
+
+        let span = token.span.to(self.previous().span);
+        Ok(Box::new(CallExpr{ name: token.lexeme.to_string(), token: token.clone(), arguments, named_arguments, span }))
+    }
+
+    /// Parses the parenthesized arguments of a call, `name=value` arguments
+    /// are collected into `named_arguments` and have to come last
+    fn parse_arguments(
+        &mut self,
+        named_arguments: &mut Vec<(Token, Box<dyn Expression>)>
+    ) -> ParseResult<Vec<Box<dyn Expression>>> {
+        let left_paren_token = self.advance();
+
+        let mut arguments: Vec<Box<dyn Expression>> = vec![];
+        let mut current_token = self.advance();
+
+        while current_token.token_type != TokenType::RightParen {
+            if self.is_at_end() {
+                return Err(self.syntax_error(&left_paren_token, "Missing right paren after function call"));
+            }
+
+            if current_token.token_type == TokenType::IDENTIFIER && self.peek().token_type == TokenType::EQUAL {
+                if named_arguments.iter().any(|(name, _)| name.lexeme == current_token.lexeme) {
+                    return Err(self.syntax_error(&current_token, "Duplicate named argument"));
+                }
+
+                // equal
+                self.advance();
+                let value_token = self.advance();
+                let value = self.parse_operation(&value_token, LOWEST_PRECEDENCE)?;
+                named_arguments.push((current_token, value));
+            } else if named_arguments.is_empty() {
+                arguments.push(self.parse_operation(&current_token, LOWEST_PRECEDENCE)?);
+            } else {
+                return Err(self.syntax_error(&current_token, "Positional argument after named argument"));
+            }
+
+            current_token = self.advance();
+
+            if current_token.token_type == TokenType::COMMA {
+                current_token = self.advance();
+            } else if current_token.token_type != TokenType::RightParen {
+                return Err(self.syntax_error(&current_token, "Comma missing after argument"));
+            }
+        }
+
+        Ok(arguments)
     }
 
-    fn parse_function(&mut self) -> Result<Box<dyn Expression>, &str> {
+    /// Parses a function declaration, `owner` is the type of a surrounding `impl` block
+    fn parse_function(&mut self, owner: Option<&str>) -> ParseResult<Rc<FunctionExpr>> {
 
         let identifier_token = self.advance();
         if identifier_token.token_type != TokenType::IDENTIFIER {
-            return Err("Missing identifier after function keyword");
+            return Err(self.syntax_error(&identifier_token, "Missing identifier after function keyword"));
         }
 
         let left_paren_token = self.advance();
         if left_paren_token.token_type != TokenType::LeftParen {
-            return Err("Missing left paren after function name");
+            return Err(self.syntax_error(&left_paren_token, "Missing left paren after function name"));
         }
 
-        let right_paren_token = self.advance();
-        if right_paren_token.token_type != TokenType::RightParen {
-            return Err("Missing right paren after after all params");
+        let parameters = self.parse_parameters(owner)?;
+
+        let return_type: Box<dyn Expression> = if self.peek().token_type == TokenType::ARROW {
+            self.advance();
+            self.parse_type()?
+        } else {
+            Box::new(EmptyExpr{})
+        };
+
+        let body_expr_list = self.parse_function_body(|parser| parser.parse_block())?;
+
+        Ok(Rc::new(FunctionExpr {
+            name: identifier_token.lexeme.to_string(),
+            token: identifier_token,
+            owner: owner.map(String::from),
+            parameters,
+            return_type,
+            body: body_expr_list
+        }))
+    }
+
+    /// Parses an anonymous function, either `fun (x) -> x + 1` or
+    /// a parameter list followed by an indented body
+    fn parse_lambda(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        let left_paren_token = self.advance();
+        if left_paren_token.token_type != TokenType::LeftParen {
+            return Err(self.syntax_error(&left_paren_token, "Missing left paren after function keyword"));
         }
 
-        // ignore line break
-        self.advance();
+        let parameters = self.parse_parameters(None)?;
+
+        let body = if self.peek().token_type == TokenType::ARROW {
+            let keyword = self.advance();
+            self.parse_function_body(|parser| {
+                let token = parser.advance();
+                let value = parser.parse_operation(&token, LOWEST_PRECEDENCE)?;
+                Ok(vec![Box::new(ReturnExpr { keyword, value }) as Box<dyn Expression>])
+            })?
+        } else {
+            self.parse_function_body(|parser| parser.parse_block())?
+        };
+
+        let function = Rc::new(FunctionExpr {
+            name: String::from("lambda"),
+            token: token.clone(),
+            owner: None,
+            parameters,
+            return_type: Box::new(EmptyExpr{}),
+            body,
+        });
+
+        Ok(Box::new(LambdaExpr { token: token.clone(), function }))
+    }
+
+    /// Parses the parameters of a function after its left paren
+    fn parse_parameters(&mut self, owner: Option<&str>) -> ParseResult<Vec<Parameter>> {
+        let mut parameters: Vec<Parameter> = vec![];
+        let mut current_token = self.advance();
+
+        while current_token.token_type != TokenType::RightParen {
+            if current_token.token_type != TokenType::IDENTIFIER {
+                return Err(self.syntax_error(&current_token, "Missing right paren after after all params"));
+            }
+
+            if parameters.iter().any(|parameter| parameter.name == current_token.lexeme) {
+                return Err(self.duplicate_error(&current_token, format!("Parameter `{}` is declared twice", current_token.lexeme)));
+            }
+
+            if current_token.lexeme == "self" && (owner.is_none() || !parameters.is_empty()) {
+                return Err(self.syntax_error(&current_token, "`self` is only allowed as first parameter of a function in an impl block"));
+            }
+
+            let type_annotation = self.parse_type_annotation()?;
+            parameters.push(Parameter { name: current_token.lexeme, type_annotation });
+            current_token = self.advance();
+
+            if current_token.token_type == TokenType::COMMA {
+                current_token = self.advance();
+            } else if current_token.token_type != TokenType::RightParen {
+                return Err(self.syntax_error(&current_token, "Comma missing after parameter"));
+            }
+        }
+
+        Ok(parameters)
+    }
+
+    fn parse_function_body<T>(
+        &mut self,
+        parse_body: impl FnOnce(&mut Self) -> ParseResult<T>
+    ) -> ParseResult<T> {
+        // loops around the declaration cannot be left from inside the body
+        let surrounding_loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+        self.function_depth += 1;
+        let body = parse_body(self);
+        self.function_depth -= 1;
+        self.loop_depth = surrounding_loop_depth;
+
+        body
+    }
+
+    /// Types, impl blocks and imports belong to the module, they cannot be declared
+    /// inside a block or function body
+    fn is_top_level(&self) -> bool {
+        self.current_level == 0 && self.function_depth == 0
+    }
+
+    /// Parses `import module` or `from module import name, other`
+    fn parse_import(&mut self, keyword: TokenType) -> ParseResult<Box<dyn Expression>> {
+        if !self.is_top_level() {
+            return Err(self.syntax_error(&self.previous(), "Imports are only allowed at the top level"));
+        }
+
+        let module = self.advance();
+        if module.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&module, "Missing module name"));
+        }
+
+        let mut names: Vec<Token> = vec![];
+        if keyword == TokenType::FROM {
+            let import_token = self.advance();
+            if import_token.token_type != TokenType::IMPORT {
+                return Err(self.syntax_error(&import_token, "Missing import keyword after module name"));
+            }
 
-        let level1 = self.advance();
-        if level1.token_type != TokenType::SpaceLevel {
-            return Err("Function body is missing");
+            loop {
+                let name = self.advance();
+                if name.token_type != TokenType::IDENTIFIER {
+                    return Err(self.syntax_error(&name, "Missing name to import"));
+                }
+
+                if names.iter().any(|other| other.lexeme == name.lexeme) {
+                    return Err(self.duplicate_error(&name, format!("`{}` is imported twice", name.lexeme)));
+                }
+
+                names.push(name);
+                if self.peek().token_type != TokenType::COMMA {
+                    break;
+                }
+
+                // comma
+                self.advance();
+            }
+        }
+
+        Ok(Box::new(ImportExpr { module, names }))
+    }
+
+    /// Parses an `impl` block with one function declaration per indented line
+    fn parse_impl(&mut self) -> ParseResult<Box<dyn Expression>> {
+        if !self.is_top_level() {
+            return Err(self.syntax_error(&self.previous(), "Impl blocks are only allowed at the top level"));
+        }
+
+        let identifier_token = self.advance();
+        if identifier_token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&identifier_token, "Missing type name after impl keyword"));
+        }
+
+        let type_name = identifier_token.lexeme.to_string();
+        let functions = self.parse_indented(|parser, token| {
+            if token.token_type != TokenType::FUNCTION {
+                return Err(parser.syntax_error(&token, "Only functions can be declared in an impl block"));
+            }
+
+            parser.parse_function(Some(&type_name))
+        })?;
+
+        for (index, function) in functions.iter().enumerate() {
+            if functions[..index].iter().any(|other| other.name == function.name) {
+                return Err(self.duplicate_error(&function.token, format!("Function `{}` is declared twice in this impl block", function.name)));
+            }
+        }
+
+        Ok(Box::new(ImplExpr { type_name, token: identifier_token, functions }))
+    }
+
+    /// Parses a struct declaration with one `name: type` field per indented line
+    fn parse_struct(&mut self) -> ParseResult<Box<dyn Expression>> {
+        if !self.is_top_level() {
+            return Err(self.syntax_error(&self.previous(), "Structs are only allowed at the top level"));
+        }
+
+        let identifier_token = self.advance();
+        if identifier_token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&identifier_token, "Missing identifier after struct keyword"));
+        }
+
+        let fields = self.parse_indented(|parser, token| {
+            if token.token_type != TokenType::IDENTIFIER {
+                return Err(parser.syntax_error(&token, "Missing field name"));
+            }
+
+            let type_annotation = parser.parse_type_annotation()?;
+            Ok(Field { name: token.lexeme.to_string(), token, type_annotation })
+        })?;
+
+        for (index, field) in fields.iter().enumerate() {
+            if fields[..index].iter().any(|other| other.name == field.name) {
+                return Err(self.duplicate_error(&field.token, format!("Field `{}` is declared twice", field.name)));
+            }
+        }
+
+        Ok(Box::new(Rc::new(StructExpr { name: identifier_token.lexeme.to_string(), token: identifier_token, fields })))
+    }
+
+    /// Parses an enum declaration with one variant per indented line,
+    /// variants carrying data list their fields like parameters: `Circle(radius: float)`
+    fn parse_enum(&mut self) -> ParseResult<Box<dyn Expression>> {
+        if !self.is_top_level() {
+            return Err(self.syntax_error(&self.previous(), "Enums are only allowed at the top level"));
+        }
+
+        let identifier_token = self.advance();
+        if identifier_token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&identifier_token, "Missing identifier after enum keyword"));
         }
 
-        let mut level_number = 1;
-        let mut next_token = self.advance();
+        let variants = self.parse_indented(|parser, token| parser.parse_variant(token))?;
 
-        while next_token.token_type == TokenType::SpaceLevel {
-            level_number += 1;
-            next_token = self.advance();
+        for (index, variant) in variants.iter().enumerate() {
+            if variants[..index].iter().any(|other| other.name == variant.name) {
+                return Err(self.duplicate_error(&variant.token, format!("Variant `{}` is declared twice", variant.name)));
+            }
         }
 
-        self.current_level += level_number;
+        Ok(Box::new(Rc::new(EnumExpr { name: identifier_token.lexeme.to_string(), token: identifier_token, variants })))
+    }
 
-        let mut body_expr_list: Vec<Box<dyn Expression>> = vec![];
-        let body_expr = self.parse_expr(&next_token);
-        let mut is_body_parsing = body_expr.is_ok();
+    fn parse_variant(&mut self, token: Token) -> ParseResult<Variant> {
+        if token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&token, "Missing variant name"));
+        }
 
-        if is_body_parsing {
-            body_expr_list.push(body_expr.unwrap());
-            // ignore line breaks
+        let mut fields: Vec<Field> = vec![];
+        if self.peek().token_type == TokenType::LeftParen {
+            // left paren
             self.advance();
+
+            let mut current_token = self.advance();
+            while current_token.token_type != TokenType::RightParen {
+                if current_token.token_type != TokenType::IDENTIFIER {
+                    return Err(self.syntax_error(&current_token, "Missing right paren after variant fields"));
+                }
+
+                if fields.iter().any(|field| field.name == current_token.lexeme) {
+                    return Err(self.duplicate_error(&current_token, format!("Field `{}` is declared twice", current_token.lexeme)));
+                }
+
+                let type_annotation = self.parse_type_annotation()?;
+                fields.push(Field { name: current_token.lexeme.to_string(), token: current_token, type_annotation });
+                current_token = self.advance();
+
+                if current_token.token_type == TokenType::COMMA {
+                    current_token = self.advance();
+                } else if current_token.token_type != TokenType::RightParen {
+                    return Err(self.syntax_error(&current_token, "Comma missing after variant field"));
+                }
+            }
+        }
+
+        Ok(Variant { name: token.lexeme.to_string(), token, fields })
+    }
+
+    fn parse_match(&mut self, keyword: &Token) -> ParseResult<Box<dyn Expression>> {
+        let token = self.advance();
+        let subject = self.parse_operation(&token, LOWEST_PRECEDENCE)?;
+        let arms = self.parse_indented(|parser, token| parser.parse_match_arm(token))?;
+
+        Ok(Box::new(MatchExpr { keyword: keyword.clone(), subject, arms }))
+    }
+
+    /// Parses `pattern if guard -> body`, the body is either the rest of the line or an indented block
+    fn parse_match_arm(&mut self, token: Token) -> ParseResult<MatchArm> {
+        let mut bindings: Vec<String> = vec![];
+        let pattern = self.parse_pattern(token, &mut bindings)?;
+
+        let guard: Box<dyn Expression> = if self.peek().token_type == TokenType::IF {
+            self.advance();
+            let guard_token = self.advance();
+            self.parse_operation(&guard_token, LOWEST_PRECEDENCE)?
+        } else {
+            Box::new(EmptyExpr{})
+        };
+
+        let arrow_token = self.advance();
+        if arrow_token.token_type != TokenType::ARROW {
+            return Err(self.syntax_error(&arrow_token, "Missing `->` after match pattern"));
         }
 
-        while is_body_parsing && !self.is_at_end() {
-            for _ in 0..level_number {
-                next_token = self.advance();
+        let body = if self.peek().token_type == TokenType::LineBreak {
+            self.parse_block()?
+        } else {
+            let body_token = self.advance();
+            vec![self.parse_expr(&body_token)?]
+        };
+
+        Ok(MatchArm { pattern, guard, body })
+    }
+
+    /// Parses a pattern, `bindings` collects the bound names to reject duplicates
+    fn parse_pattern(&mut self, token: Token, bindings: &mut Vec<String>) -> ParseResult<Pattern> {
+        match token.token_type {
+            TokenType::IDENTIFIER if self.peek().token_type == TokenType::DOT => {
+                // dot
+                self.advance();
+
+                let variant = self.advance();
+                if variant.token_type != TokenType::IDENTIFIER {
+                    return Err(self.syntax_error(&variant, "Missing variant name after `.` in pattern"));
+                }
+
+                let mut fields: Vec<Pattern> = vec![];
+                if self.peek().token_type == TokenType::LeftParen {
+                    let left_paren_token = self.advance();
+
+                    let mut current_token = self.advance();
+                    while current_token.token_type != TokenType::RightParen {
+                        if self.is_at_end() {
+                            return Err(self.syntax_error(&left_paren_token, "Missing right paren after variant pattern"));
+                        }
+
+                        fields.push(self.parse_pattern(current_token, bindings)?);
+                        current_token = self.advance();
+
+                        if current_token.token_type == TokenType::COMMA {
+                            current_token = self.advance();
+                        } else if current_token.token_type != TokenType::RightParen {
+                            return Err(self.syntax_error(&current_token, "Comma missing after pattern"));
+                        }
+                    }
+                }
+
+                Ok(Pattern::Variant { enum_name: token, variant, fields })
+            },
+            TokenType::IDENTIFIER if token.lexeme == "_" => Ok(Pattern::Wildcard),
+            TokenType::IDENTIFIER => {
+                if bindings.contains(&token.lexeme) {
+                    return Err(self.duplicate_error(&token, format!("`{}` is bound twice in the same pattern", token.lexeme)));
+                }
+
+                bindings.push(token.lexeme.to_string());
+                Ok(Pattern::Binding(token))
+            },
+            TokenType::INT | TokenType::FLOAT | TokenType::STRING | TokenType::TRUE | TokenType::FALSE => {
+                Ok(Pattern::Literal(self.parse_primary(&token)?))
+            },
+            TokenType::MINUS if matches!(self.peek().token_type, TokenType::INT | TokenType::FLOAT) => {
+                Ok(Pattern::Literal(self.parse_unary(&token)?))
+            },
+            _ => Err(self.syntax_error(&token, "Invalid pattern"))
+        }
+    }
+
+    fn parse_return(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            return Err(self.syntax_error(&keyword, "`return` outside of a function"));
+        }
+
+        let next_token_type = self.peek().token_type;
+        if next_token_type == TokenType::LineBreak || next_token_type == TokenType::EOF {
+            return Ok(Box::new(ReturnExpr { keyword, value: Box::new(EmptyExpr{}) }));
+        }
+
+        let token = self.advance();
+        let value = self.parse_operation(&token, LOWEST_PRECEDENCE)?;
+
+        Ok(Box::new(ReturnExpr { keyword, value }))
+    }
+
+    fn parse_if(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let token = self.advance();
+        let condition = self.parse_operation(&token, LOWEST_PRECEDENCE)?;
+        let body = self.parse_block()?;
+        let mut else_body: Vec<Box<dyn Expression>> = vec![];
+
+        // an else branch has to start at the same level as its if
+        if self.peek_indentation() == self.current_level
+            && self.peek_at(self.current_level as usize).token_type == TokenType::ELSE {
+            for _ in 0..self.current_level {
+                self.advance();
             }
 
-            next_token = self.advance();
+            // else
+            self.advance();
 
-            let body_expr = self.parse_expr(&next_token);
-            is_body_parsing = body_expr.is_ok();
+            if self.peek().token_type == TokenType::IF {
+                self.advance();
+                else_body.push(self.parse_if()?);
+            } else {
+                else_body = self.parse_block()?;
+            }
+        }
+
+        Ok(Box::new(IfExpr { condition, body, else_body }))
+    }
 
-            if is_body_parsing {
-                body_expr_list.push(body_expr.unwrap());
-                // ignore line break
+    fn parse_while(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let token = self.advance();
+        let condition = self.parse_operation(&token, LOWEST_PRECEDENCE)?;
+
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+
+        Ok(Box::new(WhileExpr { condition, body: body? }))
+    }
+
+    fn parse_for(&mut self) -> ParseResult<Box<dyn Expression>> {
+        let token = self.advance();
+        if token.token_type != TokenType::IDENTIFIER {
+            return Err(self.syntax_error(&token, "Missing identifier after for keyword"));
+        }
+
+        let mut value_variable = None;
+        if self.peek().token_type == TokenType::COMMA {
+            self.advance();
+
+            let value_token = self.advance();
+            if value_token.token_type != TokenType::IDENTIFIER {
+                return Err(self.syntax_error(&value_token, "Missing identifier after comma in for loop"));
+            }
+
+            if value_token.lexeme == token.lexeme {
+                return Err(self.duplicate_error(&value_token, format!("Both loop variables are named `{}`", token.lexeme)));
+            }
+
+            value_variable = Some(value_token);
+        }
+
+        let in_token = self.advance();
+        if in_token.token_type != TokenType::IN {
+            return Err(self.syntax_error(&in_token, "Missing `in` after loop variable"));
+        }
+
+        let iterable_token = self.advance();
+        let iterable = self.parse_operation(&iterable_token, LOWEST_PRECEDENCE)?;
+
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+
+        Ok(Box::new(ForExpr { variable: token.lexeme.to_string(), token, value_variable, iterable, body: body? }))
+    }
+
+    fn parse_break(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        if self.loop_depth == 0 {
+            return Err(self.syntax_error(token, "`break` outside of a loop"));
+        }
+
+        Ok(Box::new(BreakExpr { keyword: token.clone() }))
+    }
+
+    fn parse_continue(&mut self, token: &Token) -> ParseResult<Box<dyn Expression>> {
+        if self.loop_depth == 0 {
+            return Err(self.syntax_error(token, "`continue` outside of a loop"));
+        }
+
+        Ok(Box::new(ContinueExpr { keyword: token.clone() }))
+    }
+
+    fn parse_block(&mut self) -> ParseResult<Vec<Box<dyn Expression>>> {
+        self.parse_indented(|parser, token| parser.parse_expr(&token))
+    }
+
+    /// Parses the indented lines following a block header like `fun`, `if` or `struct`,
+    /// handing the first token of every line to `parse_line`.
+    ///
+    /// The first line decides the level of the block, it has to be indented deeper than
+    /// the surrounding code. The block ends before the first line with less indentation.
+    /// Blank lines are skipped and the line break after the last line is consumed.
+    fn parse_indented<T>(
+        &mut self,
+        parse_line: impl FnMut(&mut Self, Token) -> ParseResult<T>
+    ) -> ParseResult<Vec<T>> {
+        let line_break_token = self.advance();
+        if line_break_token.token_type != TokenType::LineBreak {
+            return Err(self.syntax_error(&line_break_token, "Missing line break before block"));
+        }
+
+        self.skip_blank_lines();
+
+        let block_level = self.peek_indentation();
+        if block_level <= self.current_level {
+            return Err(self.syntax_error(&line_break_token, "Block body is missing"));
+        }
+
+        let surrounding_level = self.current_level;
+        self.current_level = block_level;
+        let lines = self.parse_lines(parse_line);
+        self.current_level = surrounding_level;
+
+        Ok(lines)
+    }
+
+    /// Parses the lines at the current level until the first line with less indentation.
+    ///
+    /// A line which fails to parse is reported and skipped together with the lines indented
+    /// deeper than it, which belong to its block, so one mistake yields one diagnostic and
+    /// parsing goes on with the next line of the block.
+    fn parse_lines<T>(
+        &mut self,
+        mut parse_line: impl FnMut(&mut Self, Token) -> ParseResult<T>
+    ) -> Vec<T> {
+        let level = self.current_level;
+        let mut lines: Vec<T> = vec![];
+
+        self.skip_blank_lines();
+
+        while !self.is_at_end() {
+            let indentation = self.peek_indentation();
+            if indentation < level {
+                break;
+            }
+
+            for _ in 0..level {
                 self.advance();
+            }
 
-                while !self.is_at_end() && self.peek().token_type == TokenType::LineBreak {
-                    self.advance();
+            if indentation > level {
+                let indented_token = self.peek_at((indentation - level) as usize);
+                self.report(self.syntax_error(&indented_token, "Unexpected indentation"));
+                self.synchronize();
+                continue;
+            }
+
+            let token = self.advance();
+            match parse_line(self, token.clone()) {
+                Ok(line) => {
+                    lines.push(line);
+
+                    // nested blocks already consumed the line break ending them
+                    if self.previous().token_type != TokenType::LineBreak && !self.is_at_end() {
+                        let next_token = self.advance();
+                        if next_token.token_type != TokenType::LineBreak {
+                            self.report(self.syntax_error(&next_token, "Unexpected token after expression"));
+                            self.synchronize();
+                        }
+                    }
+                },
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize();
                 }
             }
+
+            self.skip_blank_lines();
         }
 
-        self.current_level -= level_number;
+        lines
+    }
 
-        Ok(Box::new(FunctionExpr {
-            name: identifier_token.lexeme.to_string(),
-            return_type: Box::new(EmptyExpr{}),
-            body: body_expr_list
-        }))
+    fn report(&self, error: ParseError) {
+        if error.reported {
+            return;
+        }
+
+        self.reporter.report_error(error.code, &error.file_name, error.span, &error.message);
+    }
+
+    fn syntax_error(&self, token: &Token, message: &str) -> ParseError {
+        self.error(Code::SyntaxError, token, message.to_string())
+    }
+
+    fn duplicate_error(&self, token: &Token, message: String) -> ParseError {
+        self.error(Code::DuplicateDeclaration, token, message)
+    }
+
+    /// The end of the file has no place in the source, an error found there
+    /// is located at the last token of the file instead
+    fn error(&self, code: Code, token: &Token, message: String) -> ParseError {
+        let token = match token.token_type {
+            TokenType::EOF => self.tokens.iter().rev()
+                .find(|token| !matches!(token.token_type, TokenType::EOF | TokenType::LineBreak | TokenType::SpaceLevel))
+                .unwrap_or(token),
+            _ => token
+        };
+
+        let reported = self.follows_invalid_token(token);
+        ParseError { code, file_name: token.file_name.to_string(), span: token.span, message, reported }
+    }
+
+    /// Whether the token or the line parsed up to it holds a token the scanner could not
+    /// make sense of. Errors there are a consequence of the scanner error and not reported.
+    fn follows_invalid_token(&self, token: &Token) -> bool {
+        let mut consumed = self.tokens[..self.current_token_index.min(self.tokens.len())].iter().rev().peekable();
+        // the line break ending the line may have been consumed looking for the rest of it
+        consumed.next_if(|consumed_token| consumed_token.token_type == TokenType::LineBreak);
+
+        token.token_type == TokenType::INVALID || consumed
+            .take_while(|consumed_token| consumed_token.token_type != TokenType::LineBreak)
+            .any(|consumed_token| consumed_token.token_type == TokenType::INVALID)
+    }
+
+    /// Panic mode recovery after an error: skips the rest of the broken line and the lines
+    /// indented deeper than the current level, stopping at the start of the next line of
+    /// the current block
+    fn synchronize(&mut self) {
+        if !self.ended_block() {
+            self.skip_line();
+        }
+
+        loop {
+            self.skip_blank_lines();
+            if self.is_at_end() || self.peek_indentation() <= self.current_level {
+                break;
+            }
+
+            self.skip_line();
+        }
+    }
+
+    /// Skips up to and including the next line break
+    fn skip_line(&mut self) {
+        while !self.is_at_end() && self.advance().token_type != TokenType::LineBreak {}
+    }
+
+    fn skip_blank_lines(&mut self) {
+        loop {
+            let indentation = self.peek_indentation() as usize;
+            if self.peek_at(indentation).token_type != TokenType::LineBreak {
+                break;
+            }
+
+            for _ in 0..=indentation {
+                self.advance();
+            }
+        }
+    }
+
+    /// Whether the expression parsed last ended with a block, like `match`,
+    /// which already consumed the line break ending it
+    fn ended_block(&self) -> bool {
+        self.current_token_index > 0 && self.previous().token_type == TokenType::LineBreak
+    }
+
+    /// Line breaks and indentation carry no meaning inside brackets
+    fn skip_line_breaks(&mut self) {
+        while matches!(self.peek().token_type, TokenType::LineBreak | TokenType::SpaceLevel) {
+            self.advance();
+        }
+    }
+
+    fn peek_indentation(&self) -> i32 {
+        let mut index = self.current_token_index;
+
+        while index < self.tokens.len() && self.tokens[index].token_type == TokenType::SpaceLevel {
+            index += 1;
+        }
+
+        (index - self.current_token_index) as i32
     }
 
     fn is_at_end(&self) -> bool {
@@ -218,11 +1260,62 @@ impl Parser {
     fn advance(&mut self) -> Token {
         let token = self.tokens[self.current_token_index].borrow();
         self.current_token_index += 1;
-        return Token::new(token.token_type, token.lexeme.to_string(), token.file_name.to_string(), token.line);
+        return Token::new(token.token_type, token.lexeme.to_string(), token.file_name.to_string(), token.span);
+    }
+
+    fn peek_at(&self, offset: usize) -> Token {
+        let index = (self.current_token_index + offset).min(self.tokens.len() - 1);
+        self.tokens[index].clone()
+    }
+
+    fn previous(&self) -> Token {
+        self.tokens[self.current_token_index - 1].clone()
     }
 
     fn peek(&self) -> Token {
         let token = self.tokens[self.current_token_index].borrow();
-        return Token::new(token.token_type, token.lexeme.to_string(), token.file_name.to_string(), token.line);
+        return Token::new(token.token_type, token.lexeme.to_string(), token.file_name.to_string(), token.span);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_count(source: &str) -> usize {
+        let reporter = CodeReporter::new(ErrorFormat::Muted);
+        let tokens = Scanner::new(reporter.clone()).scan_string(String::from("main.hunt"), source.to_string());
+        Parser::new(tokens, reporter.clone()).parse_ast();
+
+        reporter.error_count()
+    }
+
+    #[test]
+    fn broken_string_is_reported_once() {
+        assert_eq!(error_count("const s = \"abc\nprint(s)\n"), 1);
+        assert_eq!(error_count("const s = \"abc"), 1);
+        assert_eq!(error_count("print(\"abc\nprint(1)\n"), 1);
+        assert_eq!(error_count("if true\n    print(\"abc\n    print(1)\n"), 1);
+    }
+
+    #[test]
+    fn unexpected_character_is_reported_once() {
+        assert_eq!(error_count("const s = $\n"), 1);
+        assert_eq!(error_count("print(1 $ 2)\n"), 1);
+    }
+
+    #[test]
+    fn print_requires_a_right_paren() {
+        assert_eq!(error_count("print(1,"), 1);
+        assert_eq!(error_count("print("), 1);
+        assert_eq!(error_count("print(1, 2"), 1);
+        assert_eq!(error_count("print(1,\n"), 1);
+        assert_eq!(error_count("print(1, 2)"), 0);
+        assert_eq!(error_count("print()"), 0);
+    }
+
+    #[test]
+    fn errors_after_a_broken_line_are_still_reported() {
+        assert_eq!(error_count("const s = \"abc\nconst t = )\n"), 2);
+    }
+}
@@ -1,22 +1,404 @@
-use log::warn;
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+use colored::{ColoredString, Colorize};
+use crate::codes::Code;
+use crate::scanner::Span;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// How diagnostics are printed, chosen with `--error-format`
+#[derive(Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    /// Source lines with underlined spans, for people
+    Human,
+    /// One JSON object per line, for editors and CI
+    Json,
+    /// Nothing is printed, the diagnostics are only collected
+    Muted,
+}
+
+/// Secondary span of a diagnostic with a short explanation, like the annotation a value has to match
+#[derive(Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Problem in a source file, rendered together with the lines its spans point at
+#[derive(Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    pub message: String,
+    pub file_name: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    /// Printed as `= note: ...` below the source lines
+    pub notes: Vec<String>,
+    /// Printed as `= help: ...` below the notes
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, file_name: &str, span: Span, message: &str) -> Self {
+        Diagnostic::new(Severity::Error, code, file_name, span, message)
+    }
+
+    pub fn warning(code: Code, file_name: &str, span: Span, message: &str) -> Self {
+        Diagnostic::new(Severity::Warning, code, file_name, span, message)
+    }
+
+    fn new(severity: Severity, code: Code, file_name: &str, span: Span, message: &str) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message: message.to_string(),
+            file_name: file_name.to_string(),
+            span,
+            labels: vec![],
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    /// Labels have to point into the file of the diagnostic
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label { span, message: message.to_string() });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help.push(help.to_string());
+        self
+    }
+}
+
+/// Prints and collects the diagnostics of a run. Clones share the collected
+/// diagnostics, so the scanner, the parser and the checker can each hold one.
+#[derive(Clone)]
 pub struct CodeReporter {
-    has_error: bool
+    diagnostics: Rc<RefCell<Vec<Diagnostic>>>,
+    format: ErrorFormat,
 }
 
 impl CodeReporter {
 
-    pub fn new() -> Self {
-        CodeReporter { has_error: false }
+    pub fn new(format: ErrorFormat) -> Self {
+        CodeReporter { diagnostics: Rc::new(RefCell::new(vec![])), format }
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.error_count() > 0
     }
 
-    pub fn report_error(&mut self, file: &String, line: usize, message: &String) {
-        self.has_error = true;
-        self.report(file, line, message);
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
     }
 
-    fn report(&self, file: &String, line: usize, message: &String) {
-        warn!("[{}:{}]: {}", file, line, message);
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.borrow().iter().filter(|diagnostic| diagnostic.severity == severity).count()
+    }
+
+    pub fn report_error(&self, code: Code, file: &str, span: Span, message: &str) {
+        self.report(Diagnostic::error(code, file, span, message));
+    }
+
+    /// Prints the diagnostic right away, warnings do not stop the program from running
+    pub fn report(&self, diagnostic: Diagnostic) {
+        match self.format {
+            ErrorFormat::Human => eprint!("{}", render(&diagnostic)),
+            ErrorFormat::Json => eprintln!("{}", to_json(&diagnostic)),
+            ErrorFormat::Muted => {}
+        }
+
+        self.diagnostics.borrow_mut().push(diagnostic);
+    }
+
+    /// Closing line like `error: aborting due to 2 previous errors; 1 warning emitted`,
+    /// which is left out of the machine readable format
+    pub fn report_summary(&self) {
+        if self.format != ErrorFormat::Human {
+            return;
+        }
+
+        let errors = self.error_count();
+        let warnings = self.warning_count();
+
+        let warning_text = match warnings {
+            1 => String::from("1 warning emitted"),
+            _ => format!("{} warnings emitted", warnings),
+        };
+
+        if errors > 0 {
+            let error_text = match errors {
+                1 => String::from("aborting due to 1 previous error"),
+                _ => format!("aborting due to {} previous errors", errors),
+            };
+            let warning_text = if warnings > 0 { format!("; {}", warning_text) } else { String::new() };
+
+            eprintln!("{}{} {}{}\n", "error".red().bold(), ":".bold(), error_text.bold(), warning_text.bold());
+        } else if warnings > 0 {
+            eprintln!("{}{} {}\n", "warning".yellow().bold(), ":".bold(), warning_text.bold());
+        }
+    }
+}
+
+/// Renders a diagnostic the way rustc does:
+///
+/// ```text
+/// error[H0007]: Mismatched types for `x`: expected int, found string
+///  --> main.hunt:3:16
+///   |
+/// 3 | const x: int = "a"
+///   |          --- expected due to this annotation
+///   |                ^^^
+/// ```
+///
+/// Colors are left out when stderr is no terminal or `--color=never` was given.
+pub fn render(diagnostic: &Diagnostic) -> String {
+    let highlight: fn(&str) -> ColoredString = match diagnostic.severity {
+        Severity::Error => |text| text.red().bold(),
+        Severity::Warning => |text| text.yellow().bold(),
+    };
+    let gutter = |text: &str| text.blue().bold();
+
+    let header = format!("{}[{}]", diagnostic.severity.name(), diagnostic.code.name());
+    let mut output = format!("{}{} {}\n", highlight(&header), ":".bold(), diagnostic.message.bold());
+
+    // annotations without a line, like errors at the end of a file, only show the file
+    let mut annotations: Vec<(Span, &str, bool)> = vec![(diagnostic.span, "", true)];
+    annotations.extend(diagnostic.labels.iter().map(|label| (label.span, label.message.as_str(), false)));
+    annotations.retain(|(span, _, _)| span.line > 0);
+    annotations.sort_by_key(|(span, _, _)| (span.line, span.column));
+
+    let source = fs::read_to_string(&diagnostic.file_name).unwrap_or_default();
+    let lines: Vec<&str> = source.lines().collect();
+    let width = annotations.iter().map(|(span, _, _)| span.line.to_string().len()).max().unwrap_or(1);
+    let padding = " ".repeat(width);
+
+    output += &format!("{}{} {}\n", padding, gutter("-->"), location(&diagnostic.file_name, diagnostic.span));
+
+    let annotations: Vec<_> = annotations.into_iter().filter(|(span, _, _)| span.line <= lines.len()).collect();
+    if !annotations.is_empty() {
+        output += &format!("{} {}\n", padding, gutter("|"));
+    }
+
+    let mut previous_line = 0;
+    for (span, message, primary) in &annotations {
+        let source_line = lines[span.line - 1];
+
+        if span.line != previous_line {
+            if previous_line != 0 && span.line > previous_line + 1 {
+                output += &format!("{}\n", gutter("..."));
+            }
+
+            output += &format!("{} {} {}\n", gutter(&format!("{:>width$}", span.line)), gutter("|"), source_line);
+            previous_line = span.line;
+        }
+
+        let (start, length) = underline_range(*span, source_line);
+        let indentation: String = source_line.chars().take(start).map(|character| if character == '\t' { '\t' } else { ' ' }).collect();
+        let marker = if *primary { highlight(&"^".repeat(length)) } else { gutter(&"-".repeat(length)) };
+        output += &format!("{} {} {}{}", padding, gutter("|"), indentation, marker);
+        if !message.is_empty() {
+            output += &format!(" {}", if *primary { highlight(message) } else { gutter(message) });
+        }
+        output += "\n";
     }
-}
\ No newline at end of file
+
+    if !annotations.is_empty() && (!diagnostic.notes.is_empty() || !diagnostic.help.is_empty()) {
+        output += &format!("{} {}\n", padding, gutter("|"));
+    }
+
+    for note in &diagnostic.notes {
+        output += &format!("{} {} {}: {}\n", padding, gutter("="), "note".bold(), note);
+    }
+
+    for help in &diagnostic.help {
+        output += &format!("{} {} {}: {}\n", padding, gutter("="), "help".bold(), help);
+    }
+
+    output + "\n"
+}
+
+/// Diagnostic as a single line of JSON, shaped like the output of `rustc --error-format=json`:
+///
+/// ```text
+/// {"message":"Division by zero","code":{"code":"H0012","explanation":"..."},"level":"error",
+///  "spans":[{"file_name":"main.hunt","byte_start":14,"byte_end":19,"line_start":2,"line_end":2,
+///  "column_start":7,"column_end":12,"is_primary":true,"label":null}],
+///  "children":[{"message":"in `divide`","level":"note"}]}
+/// ```
+///
+/// Columns start at 1 and `column_end` is exclusive. Spans without a location are left out.
+pub fn to_json(diagnostic: &Diagnostic) -> String {
+    let mut spans = vec![];
+    if diagnostic.span.line > 0 {
+        spans.push(span_json(&diagnostic.file_name, diagnostic.span, true, None));
+    }
+
+    for label in diagnostic.labels.iter().filter(|label| label.span.line > 0) {
+        spans.push(span_json(&diagnostic.file_name, label.span, false, Some(&label.message)));
+    }
+
+    let children: Vec<String> = diagnostic.notes.iter().map(|note| (note, "note"))
+        .chain(diagnostic.help.iter().map(|help| (help, "help")))
+        .map(|(message, level)| format!("{{\"message\":{},\"level\":\"{}\"}}", json_string(message), level))
+        .collect();
+
+    format!(
+        "{{\"message\":{},\"code\":{{\"code\":\"{}\",\"explanation\":{}}},\"level\":\"{}\",\"spans\":[{}],\"children\":[{}]}}",
+        json_string(&diagnostic.message),
+        diagnostic.code.name(),
+        json_string(diagnostic.code.explanation()),
+        diagnostic.severity.name(),
+        spans.join(","),
+        children.join(",")
+    )
+}
+
+fn span_json(file_name: &str, span: Span, is_primary: bool, label: Option<&str>) -> String {
+    // spans which only know their line cover all of it
+    let (column_start, column_end) = if span.column == 0 {
+        let source = fs::read_to_string(file_name).unwrap_or_default();
+        let line_length = source.lines().nth(span.line - 1).map(|line| line.chars().count()).unwrap_or(0);
+        (1, line_length + 1)
+    } else {
+        (span.column, span.end_column)
+    };
+
+    format!(
+        "{{\"file_name\":{},\"byte_start\":{},\"byte_end\":{},\"line_start\":{},\"line_end\":{},\"column_start\":{},\"column_end\":{},\"is_primary\":{},\"label\":{}}}",
+        json_string(file_name),
+        span.start,
+        span.end,
+        span.line,
+        span.end_line,
+        column_start,
+        column_end,
+        is_primary,
+        label.map(json_string).unwrap_or(String::from("null"))
+    )
+}
+
+/// Quoted JSON string with quotes, backslashes and control characters escaped
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+
+    for character in text.chars() {
+        match character {
+            '"' => output += "\\\"",
+            '\\' => output += "\\\\",
+            '\n' => output += "\\n",
+            '\r' => output += "\\r",
+            '\t' => output += "\\t",
+            character if (character as u32) < 0x20 => output += &format!("\\u{:04x}", character as u32),
+            character => output.push(character),
+        }
+    }
+
+    output + "\""
+}
+
+/// First character and number of characters to underline on the first line of `span`.
+/// Spans which only know their line underline the line without its indentation, spans
+/// reaching into later lines underline the rest of their first line.
+fn underline_range(span: Span, source_line: &str) -> (usize, usize) {
+    let line_length = source_line.chars().count();
+
+    if span.column == 0 {
+        let indentation = source_line.chars().take_while(|character| character.is_whitespace()).count();
+        return (indentation, line_length.saturating_sub(indentation).max(1));
+    }
+
+    let start = span.column - 1;
+    let end = if span.end_line == span.line { span.end_column - 1 } else { line_length };
+
+    (start, end.saturating_sub(start).max(1))
+}
+
+/// `file:line:column`, without the column if only the line is known
+fn location(file: &str, span: Span) -> String {
+    if span.line == 0 {
+        return file.to_string();
+    }
+
+    if span.column == 0 {
+        return format!("{}:{}", file, span.line);
+    }
+
+    format!("{}:{}:{}", file, span.line, span.column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: usize, column: usize, end_column: usize) -> Span {
+        Span { start: column - 1, end: end_column - 1, line, column, end_line: line, end_column }
+    }
+
+    #[test]
+    fn json_string_escapes_quotes_and_backslashes() {
+        assert_eq!(json_string(r#"say "hi" \ bye"#), r#""say \"hi\" \\ bye""#);
+        assert_eq!(json_string("é ü 😀"), "\"é ü 😀\"");
+    }
+
+    #[test]
+    fn json_string_escapes_control_characters() {
+        assert_eq!(json_string("a\nb\rc\td"), r#""a\nb\rc\td""#);
+        assert_eq!(json_string("\u{0}\u{1b}\u{1f}"), r#""\u0000\u001b\u001f""#);
+        assert_eq!(json_string("\u{7f}"), "\"\u{7f}\"");
+    }
+
+    #[test]
+    fn to_json_leaves_out_spans_without_location() {
+        let diagnostic = Diagnostic::error(Code::MismatchedTypes, "main.hunt", span(2, 7, 12), "Expected int")
+            .with_label(span(1, 10, 13), "declared here")
+            .with_label(Span::default(), "builtin");
+
+        let json = to_json(&diagnostic);
+
+        assert_eq!(json.matches("\"file_name\"").count(), 2);
+        assert!(json.contains("\"label\":\"declared here\""));
+        assert!(!json.contains("builtin"));
+    }
+
+    #[test]
+    fn to_json_lists_notes_and_help_as_children() {
+        let diagnostic = Diagnostic::error(Code::RuntimeError, "main.hunt", Span::default(), "Division by zero")
+            .with_note("in `divide`")
+            .with_help("check the divisor");
+
+        let expected = format!(
+            r#"{{"message":"Division by zero","code":{{"code":"H0012","explanation":{}}},"level":"error","spans":[],"children":[{}]}}"#,
+            json_string(Code::RuntimeError.explanation()),
+            r#"{"message":"in `divide`","level":"note"},{"message":"check the divisor","level":"help"}"#
+        );
+
+        assert_eq!(to_json(&diagnostic), expected);
+    }
+}
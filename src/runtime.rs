@@ -1,64 +1,1317 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use crate::expressions::{ConstExpr, EmptyExpr, Expression, ExpressionVisitor, FunctionExpr, IntExpr, PrintExpr, StringExpr, VariableExpr};
+use std::rc::Rc;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use crate::expressions::{ApplyExpr, AssignExpr, BinaryExpr, BoolExpr, BreakExpr, CallExpr, ConstExpr, ContinueExpr, EmptyExpr, EnumExpr, Expression, ExpressionKind, ExpressionVisitor, FloatExpr, ForExpr, FunctionExpr, GetExpr, IfExpr, ImplExpr, ImportExpr, IndexExpr, IntExpr, LambdaExpr, ListExpr, MapExpr, MatchArm, MatchExpr, MethodCallExpr, Pattern, PrintExpr, ReturnExpr, SliceExpr, StringExpr, StructExpr, TypeExpr, UnaryExpr, VarExpr, VariableExpr, WhileExpr};
+use crate::builtins;
+use crate::environment::{AssignmentError, Environment};
+use crate::modules::{self, Module, Program};
 use crate::parser::{Ast};
+use crate::scanner::{Span, Token, TokenType};
+use indexmap::IndexMap;
+use crate::value::{Closure, EnumValue, MapKey, ModuleValue, StructValue, Value};
+
+pub struct RuntimeError {
+    pub message: String,
+    /// File and span of the expression which failed, if the error can be located
+    pub location: Option<(String, Span)>,
+    /// Names of the functions the error unwound through, innermost first
+    pub trace: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn new(message: String) -> Self {
+        RuntimeError { message, location: None, trace: vec![] }
+    }
+
+    pub fn at(token: &Token, message: String) -> Self {
+        RuntimeError { message, location: Some((token.file_name.to_string(), token.span)), trace: vec![] }
+    }
+
+    /// Points the error at a whole expression of the file it was located in
+    pub fn spanning(mut self, span: Span) -> Self {
+        if let Some((_, location)) = &mut self.location {
+            *location = span;
+        }
+
+        self
+    }
+}
+
+/// Longer traces are cut off, the last line tells how many were left out
+const MAX_TRACE_LINES: usize = 16;
+
+impl RuntimeError {
+    /// One line per function the error unwound through, innermost first.
+    /// Recursion shows up once together with its depth, mutual recursion
+    /// once per cycle of functions, like ``in `even`, `odd` (128 times)``.
+    pub fn trace_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        let mut index = 0;
+        while index < self.trace.len() {
+            let (length, repeated) = repetition(&self.trace[index..]);
+            let functions: Vec<String> = self.trace[index..index + length].iter()
+                .map(|function| format!("`{}`", function))
+                .collect();
+
+            if repeated > 1 {
+                lines.push(format!("in {} ({} times)", functions.join(", "), repeated));
+            } else {
+                lines.push(format!("in {}", functions.join(", ")));
+            }
+
+            index += length * repeated;
+        }
+
+        if lines.len() > MAX_TRACE_LINES {
+            let hidden = lines.len() - MAX_TRACE_LINES;
+            lines.truncate(MAX_TRACE_LINES);
+            lines.push(format!("... {} more", hidden));
+        }
+
+        lines
+    }
+}
+
+/// Length of the shortest cycle of functions which directly repeats at the start
+/// of a trace and how often it does, `(1, 1)` if nothing repeats
+fn repetition(trace: &[String]) -> (usize, usize) {
+    for length in 1..=trace.len() / 2 {
+        let repeated = trace.chunks_exact(length).take_while(|cycle| *cycle == &trace[..length]).count();
+        if repeated > 1 {
+            return (length, repeated);
+        }
+    }
+
+    (1, 1)
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+
+        for line in self.trace_lines() {
+            write!(f, "\n    {}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Everything that unwinds the evaluation of an expression: errors as well as
+/// `break` and `continue`, which travel up to the closest enclosing loop, and
+/// `return`, which travels up to the closest function call.
+pub enum ControlFlow {
+    Error(RuntimeError),
+    Break,
+    Continue,
+    Return(Value),
+}
+
+impl From<RuntimeError> for ControlFlow {
+    fn from(error: RuntimeError) -> Self {
+        ControlFlow::Error(error)
+    }
+}
+
+pub type RuntimeResult = Result<Value, ControlFlow>;
 
 pub trait RuntimeEngine {
 
-    fn execute_ast(&self, ast: &Ast);
-    fn execute_expr(&self, expr: &Box<dyn Expression>);
+    fn execute_ast(&mut self, ast: &Ast) -> Result<(), RuntimeError>;
+    fn execute_expr(&mut self, expr: &dyn Expression) -> RuntimeResult;
+
+}
+
+/// Calls which may be nested before a program fails with a stack overflow,
+/// the stack of the interpreter thread is sized for them
+const MAX_CALL_DEPTH: usize = 5000;
+
+struct CallFrame {
+    function: String,
+}
 
+/// Functions and types declared by one module
+#[derive(Default)]
+pub struct Definitions {
+    /// Functions by owning type, free functions are stored under `None`
+    functions: HashMap<Option<String>, HashMap<String, Rc<Closure>>>,
+    structs: HashMap<String, Rc<StructExpr>>,
+    enums: HashMap<String, Rc<EnumExpr>>,
 }
 
 pub struct InterpreterRuntime {
-    functions: HashMap<String, FunctionExpr>
+    /// Definitions of the module whose code is running, a call switches to the module of the function
+    definitions: Rc<RefCell<Definitions>>,
+    environment: Rc<RefCell<Environment>>,
+    /// Top level scope of the module whose code is running
+    globals: Rc<RefCell<Environment>>,
+    call_stack: Vec<CallFrame>,
+    /// Exports of the modules executed so far by their key
+    modules: HashMap<String, Rc<ModuleValue>>,
+    module_definitions: Vec<Rc<RefCell<Definitions>>>,
 }
 
 impl InterpreterRuntime {
     pub fn new() -> Self {
+        let environment = Environment::new();
         InterpreterRuntime {
-            functions: HashMap::new()
+            definitions: Rc::new(RefCell::new(Definitions::default())),
+            globals: environment.clone(),
+            environment,
+            call_stack: vec![],
+            modules: HashMap::new(),
+            module_definitions: vec![],
         }
     }
+
+    /// Runs every module of the program once, in the order they were loaded
+    pub fn execute_program(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        for module in &program.modules {
+            self.execute_module(module)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs a module in its own global scope and keeps its top level functions and constants
+    fn execute_module(&mut self, module: &Module) -> Result<(), RuntimeError> {
+        self.definitions = Rc::new(RefCell::new(Definitions::default()));
+        self.environment = Environment::new();
+        self.globals = self.environment.clone();
+        self.module_definitions.push(self.definitions.clone());
+
+        self.declare(&module.ast);
+        self.execute_ast(&module.ast)?;
+
+        let mut exports = IndexMap::new();
+        for expression in &module.ast.expressions {
+            match expression.kind() {
+                ExpressionKind::Func(function) => {
+                    if let Some(closure) = self.function(None, &function.name) {
+                        exports.insert(function.name.to_string(), Value::Function(closure));
+                    }
+                },
+                ExpressionKind::Const(constant) => {
+                    if let Some(value) = self.environment.borrow().get(&constant.variable) {
+                        exports.insert(constant.variable.to_string(), value);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let name = Path::new(&module.file_name).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        self.modules.insert(module.key.to_string(), Rc::new(ModuleValue { name, exports }));
+
+        Ok(())
+    }
+
+    /// Registers the top level functions and types of a module before it runs,
+    /// so like in the type checker they can be used before their declaration
+    fn declare(&mut self, ast: &Ast) {
+        for expression in &ast.expressions {
+            match expression.kind() {
+                ExpressionKind::Func(function) => self.declare_function(function),
+                ExpressionKind::Impl(implementation) => {
+                    for function in &implementation.functions {
+                        self.declare_function(function);
+                    }
+                },
+                ExpressionKind::Struct(definition) => {
+                    self.definitions.borrow_mut().structs.insert(definition.name.to_string(), definition.clone());
+                },
+                ExpressionKind::Enum(definition) => {
+                    self.definitions.borrow_mut().enums.insert(definition.name.to_string(), definition.clone());
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_function(&mut self, function: &Rc<FunctionExpr>) {
+        let closure = self.closure(function);
+        self.definitions.borrow_mut().functions.entry(function.owner.clone()).or_default().insert(function.name.to_string(), closure);
+    }
+
+    fn closure(&self, function: &Rc<FunctionExpr>) -> Rc<Closure> {
+        Rc::new(Closure {
+            function: Rc::clone(function),
+            environment: self.environment.clone(),
+            definitions: self.definitions.clone(),
+        })
+    }
+
+    /// Runs the body in the given scope and restores the current scope afterwards
+    fn execute_scoped(&mut self, body: &[Box<dyn Expression>], environment: Rc<RefCell<Environment>>) -> Result<(), ControlFlow> {
+        let surrounding_environment = std::mem::replace(&mut self.environment, environment);
+        let result = self.execute_body(body);
+        self.environment = surrounding_environment;
+
+        result
+    }
+
+    fn define(&mut self, token: &Token, value: Value, mutable: bool) -> Result<(), RuntimeError> {
+        if !self.environment.borrow_mut().define(token.lexeme.to_string(), value, mutable) {
+            return Err(RuntimeError::at(token, format!("`{}` is already defined in this scope", token.lexeme)));
+        }
+
+        Ok(())
+    }
+
+    fn execute_block(&mut self, body: &[Box<dyn Expression>]) -> Result<(), ControlFlow> {
+        let environment = Environment::with_parent(self.environment.clone());
+        self.execute_scoped(body, environment)
+    }
+
+    /// Creates a struct instance, every field has to be given exactly once by name
+    fn construct(&mut self, definition: &StructExpr, expr: &CallExpr) -> RuntimeResult {
+        if !expr.arguments.is_empty() {
+            return Err(RuntimeError::at(&expr.token, format!(
+                "Struct `{}` has to be constructed with named fields", definition.name
+            )).into());
+        }
+
+        for (name, _) in &expr.named_arguments {
+            if !definition.fields.iter().any(|field| field.name == name.lexeme) {
+                return Err(RuntimeError::at(name, format!("Struct `{}` has no field `{}`", definition.name, name.lexeme)).into());
+            }
+        }
+
+        if let Some(field) = definition.fields.iter().find(|field| !expr.named_arguments.iter().any(|(name, _)| name.lexeme == field.name)) {
+            return Err(RuntimeError::at(&expr.token, format!("Missing field `{}` for struct `{}`", field.name, definition.name)).into());
+        }
+
+        // arguments are evaluated in the order they are written, the fields keep the declaration order
+        let mut values: HashMap<&str, Value> = HashMap::new();
+        for (name, value_expr) in &expr.named_arguments {
+            values.insert(&name.lexeme, self.execute_expr(value_expr.as_ref())?);
+        }
+
+        let fields = definition.fields.iter()
+            .map(|field| (field.name.to_string(), values.remove(field.name.as_str()).unwrap()))
+            .collect();
+
+        Ok(Value::Struct(Rc::new(RefCell::new(StructValue { name: definition.name.to_string(), fields }))))
+    }
+
+    /// Type named by the object of `Shape.Circle` or `Point.origin()`, unless a variable shadows the type name
+    fn type_of(&self, object: &dyn Expression) -> Option<String> {
+        match object.kind() {
+            ExpressionKind::Variable(variable) if self.environment.borrow().get(&variable.name).is_none()
+                && (self.definitions.borrow().structs.contains_key(&variable.name)
+                    || self.definitions.borrow().enums.contains_key(&variable.name)) => {
+                Some(variable.name.to_string())
+            },
+            _ => None
+        }
+    }
+
+    /// The function is cloned out of the map so the body can register
+    /// further functions while it is being executed
+    fn function(&self, owner: Option<&str>, name: &str) -> Option<Rc<Closure>> {
+        self.definitions.borrow().functions.get(&owner.map(String::from))?.get(name).cloned()
+    }
+
+    /// Function of a type declared in the running module, or in any other module for
+    /// values of types which were created by functions of that module
+    fn method(&self, type_name: &str, name: &str) -> Option<Rc<Closure>> {
+        self.function(Some(type_name), name).or_else(|| {
+            self.module_definitions.iter().find_map(|definitions| {
+                definitions.borrow().functions.get(&Some(type_name.to_string()))?.get(name).cloned()
+            })
+        })
+    }
+
+    fn enum_definition(&self, name: &str) -> Option<Rc<EnumExpr>> {
+        self.definitions.borrow().enums.get(name).cloned()
+    }
+
+    /// Exported function or constant of an imported module
+    fn export(&self, module: &ModuleValue, token: &Token) -> Result<Value, RuntimeError> {
+        match module.exports.get(&token.lexeme) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::at(token, format!("Module `{}` has no export `{}`", module.name, token.lexeme)))
+        }
+    }
+
+    /// Calls a function value on behalf of a builtin like `map`
+    pub fn call_value(&mut self, callee: &Value, token: &Token, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let closure = match callee {
+            Value::Function(closure) => closure,
+            other => return Err(RuntimeError::at(token, format!("Cannot call a value of type {}", other.type_name())))
+        };
+
+        // `break`, `continue` and `return` cannot leave a function body
+        match self.call_function(closure, token, arguments) {
+            Ok(value) => Ok(value),
+            Err(ControlFlow::Error(error)) => Err(error),
+            Err(..) => Err(RuntimeError::at(token, String::from("Unexpected control flow in function call"))),
+        }
+    }
+
+    /// Calls a user defined function with already evaluated arguments,
+    /// a method receives its instance as first argument
+    fn call_function(&mut self, closure: &Closure, token: &Token, arguments: Vec<Value>) -> RuntimeResult {
+        let function = &closure.function;
+        let name = function.qualified_name();
+
+        if function.parameters.len() != arguments.len() {
+            return Err(RuntimeError::at(token, format!(
+                "Function `{}` expects {} arguments but got {}", name, function.parameters.len(), arguments.len()
+            )).into());
+        }
+
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::at(token, format!("Stack overflow while calling `{}`", name)).into());
+        }
+
+        // the body sees the scope the function was declared in and its parameters
+        let locals = Environment::with_parent(closure.environment.clone());
+        let surrounding_definitions = std::mem::replace(&mut self.definitions, closure.definitions.clone());
+        for (parameter, value) in function.parameters.iter().zip(arguments) {
+            locals.borrow_mut().define(parameter.name.to_string(), value, true);
+        }
+
+        self.call_stack.push(CallFrame { function: name });
+        let result = self.execute_scoped(&function.body, locals);
+        let frame = self.call_stack.pop().unwrap();
+        self.definitions = surrounding_definitions;
+
+        match result {
+            Ok(()) => Ok(Value::Empty),
+            Err(ControlFlow::Return(value)) => Ok(value),
+            Err(ControlFlow::Error(mut error)) => {
+                error.trace.push(frame.function);
+                Err(error.into())
+            }
+            Err(control_flow) => Err(control_flow),
+        }
+    }
+
+    /// `Type.name(...)` constructs an enum variant or calls a function of the type which is no method
+    fn call_on_type(&mut self, type_name: &str, expr: &MethodCallExpr, arguments: Vec<Value>) -> RuntimeResult {
+        let definition = self.enum_definition(type_name);
+        if let Some(definition) = &definition {
+            if definition.variants.iter().any(|variant| variant.name == expr.name) {
+                return self.construct_variant(definition, &expr.token, arguments);
+            }
+        }
+
+        match self.function(Some(type_name), &expr.name) {
+            Some(closure) if closure.function.is_method() => Err(RuntimeError::at(&expr.token, format!(
+                "`{}` is a method and has to be called on an instance", closure.function.qualified_name()
+            )).into()),
+            Some(closure) => self.call_function(&closure, &expr.token, arguments),
+            None => match definition {
+                Some(..) => Err(RuntimeError::at(&expr.token, format!("Enum `{}` has no variant `{}`", type_name, expr.name)).into()),
+                None => Err(RuntimeError::at(&expr.token, format!("Type `{}` has no function `{}`", type_name, expr.name)).into()),
+            }
+        }
+    }
+
+    fn construct_variant(&self, definition: &EnumExpr, token: &Token, values: Vec<Value>) -> RuntimeResult {
+        let variant = match definition.variants.iter().find(|variant| variant.name == token.lexeme) {
+            Some(variant) => variant,
+            None => return Err(RuntimeError::at(token, format!("Enum `{}` has no variant `{}`", definition.name, token.lexeme)).into())
+        };
+
+        if variant.fields.len() != values.len() {
+            return Err(RuntimeError::at(token, format!(
+                "Variant `{}.{}` expects {} values but got {}", definition.name, variant.name, variant.fields.len(), values.len()
+            )).into());
+        }
+
+        Ok(Value::Enum(Rc::new(EnumValue {
+            enum_name: definition.name.to_string(),
+            variant: variant.name.to_string(),
+            values,
+        })))
+    }
+
+    /// Runs a match arm in the current scope if its pattern and guard match,
+    /// returns the value of its last expression
+    fn execute_arm(&mut self, arm: &MatchArm, subject: &Value) -> Result<Option<Value>, ControlFlow> {
+        if !self.match_pattern(&arm.pattern, subject)? {
+            return Ok(None);
+        }
+
+        if !matches!(arm.guard.kind(), ExpressionKind::Empty(..)) && !self.execute_expr(arm.guard.as_ref())?.is_truthy() {
+            return Ok(None);
+        }
+
+        let mut value = Value::Empty;
+        for body_expr in &arm.body {
+            value = self.execute_expr(body_expr.as_ref())?;
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Checks a value against a pattern and binds the names of the pattern in the current scope
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Value) -> Result<bool, ControlFlow> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding(name) => {
+                self.define(name, value.clone(), false)?;
+                Ok(true)
+            },
+            Pattern::Literal(literal) => {
+                let literal = self.execute_expr(literal.as_ref())?;
+                Ok(values_equal(&literal, value))
+            },
+            Pattern::Variant { enum_name, variant, fields } => {
+                let value = match value {
+                    Value::Enum(value) if value.enum_name == enum_name.lexeme && value.variant == variant.lexeme => value,
+                    _ => return Ok(false)
+                };
+
+                if value.values.len() != fields.len() {
+                    return Err(RuntimeError::at(variant, format!(
+                        "Variant `{}.{}` has {} values but the pattern has {}",
+                        enum_name.lexeme, variant.lexeme, value.values.len(), fields.len()
+                    )).into());
+                }
+
+                for (field, field_value) in fields.iter().zip(&value.values) {
+                    if !self.match_pattern(field, field_value)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+        }
+    }
+
+    fn call_builtin(&mut self, expr: &CallExpr) -> RuntimeResult {
+        let builtin = match builtins::find(&expr.name) {
+            Some(builtin) => builtin,
+            None => return Err(RuntimeError::at(&expr.token, format!("Undefined function `{}`", expr.name)).into()),
+        };
+
+        if let Some((name, _)) = expr.named_arguments.first() {
+            return Err(RuntimeError::at(name, format!("Function `{}` does not take named arguments", builtin.name)).into());
+        }
+
+        if builtin.arity != expr.arguments.len() {
+            return Err(RuntimeError::at(&expr.token, format!(
+                "Function `{}` expects {} arguments but got {}", builtin.name, builtin.arity, expr.arguments.len()
+            )).into());
+        }
+
+        let mut arguments: Vec<Value> = vec![];
+        for argument in &expr.arguments {
+            arguments.push(self.execute_expr(argument.as_ref())?);
+        }
+
+        Ok((builtin.function)(self, &expr.token, &arguments)?)
+    }
+
+    fn execute_body(&mut self, body: &[Box<dyn Expression>]) -> Result<(), ControlFlow> {
+        for body_expr in body {
+            self.execute_expr(body_expr.as_ref())?;
+        }
+
+        Ok(())
+    }
 }
 
 impl RuntimeEngine for InterpreterRuntime {
-    fn execute_ast(&self, ast: &Ast) {
-        for expression in &ast.expressions {
-            self.execute_expr(expression);
+    fn execute_ast(&mut self, ast: &Ast) -> Result<(), RuntimeError> {
+        match self.execute_body(&ast.expressions) {
+            Ok(()) => Ok(()),
+            Err(ControlFlow::Error(error)) => Err(error),
+            Err(ControlFlow::Break) => Err(RuntimeError::new(String::from("`break` outside of a loop"))),
+            Err(ControlFlow::Continue) => Err(RuntimeError::new(String::from("`continue` outside of a loop"))),
+            Err(ControlFlow::Return(..)) => Err(RuntimeError::new(String::from("`return` outside of a function"))),
         }
     }
 
-    fn execute_expr(&self, expr: &Box<dyn Expression>) {
+    fn execute_expr(&mut self, expr: &dyn Expression) -> RuntimeResult {
         expr.accept(self)
     }
 }
 
-impl ExpressionVisitor for InterpreterRuntime {
-    fn accept_empty(&self, expr: &EmptyExpr) {
-        todo!("accept_empty")
+impl ExpressionVisitor<RuntimeResult> for InterpreterRuntime {
+    fn accept_empty(&mut self, _expr: &EmptyExpr) -> RuntimeResult {
+        Ok(Value::Empty)
     }
 
-    fn accept_const(&self, expr: &ConstExpr) {
-        todo!("accept_const")
+    fn accept_const(&mut self, expr: &ConstExpr) -> RuntimeResult {
+        let value = self.execute_expr(expr.value.as_ref())?;
+        self.define(&expr.token, value, false)?;
+
+        Ok(Value::Empty)
     }
 
-    fn accept_func(&self, expr: &FunctionExpr) {
-        todo!("accept_func")
+    /// Top level functions and functions of impl blocks have been declared before the module
+    /// ran, a function declared inside a block is bound in the scope of the block like a constant
+    fn accept_func(&mut self, expr: &Rc<FunctionExpr>) -> RuntimeResult {
+        if expr.owner.is_none() && !Rc::ptr_eq(&self.environment, &self.globals) {
+            let closure = self.closure(expr);
+            self.define(&expr.token, Value::Function(closure), false)?;
+        }
+
+        Ok(Value::Empty)
     }
 
-    fn accept_print(&self, expr: &PrintExpr) {
-        todo!()
+    fn accept_print(&mut self, expr: &PrintExpr) -> RuntimeResult {
+        let mut output: Vec<String> = vec![];
+
+        for value_expr in &expr.values {
+            let value = self.execute_expr(value_expr.as_ref())?;
+            output.push(value.to_string());
+        }
+
+        println!("{}", output.join(" "));
+        Ok(Value::Empty)
     }
 
-    fn accept_string(&self, expr: &StringExpr) {
-        todo!()
+    fn accept_string(&mut self, expr: &StringExpr) -> RuntimeResult {
+        Ok(Value::Str(expr.value.to_string()))
     }
 
-    fn accept_int(&self, expr: &IntExpr) {
-        todo!()
+    fn accept_int(&mut self, expr: &IntExpr) -> RuntimeResult {
+        Ok(Value::Int(expr.value))
     }
 
-    fn accept_variable(&self, expr: &VariableExpr) {
-        todo!()
+    fn accept_float(&mut self, expr: &FloatExpr) -> RuntimeResult {
+        Ok(Value::Float(expr.value))
     }
-}
\ No newline at end of file
+
+    fn accept_bool(&mut self, expr: &BoolExpr) -> RuntimeResult {
+        Ok(Value::Bool(expr.value))
+    }
+
+    /// Variables shadow functions, a function name which is not shadowed evaluates to the function
+    fn accept_variable(&mut self, expr: &VariableExpr) -> RuntimeResult {
+        if let Some(value) = self.environment.borrow().get(&expr.name) {
+            return Ok(value);
+        }
+
+        match self.function(None, &expr.name) {
+            Some(closure) => Ok(Value::Function(closure)),
+            None => Err(RuntimeError::at(&expr.token, format!("Undefined variable `{}`", expr.name)).into()),
+        }
+    }
+
+    /// Variables shadow functions, types and builtins of the same name,
+    /// calling one which holds no function is an error
+    fn accept_call(&mut self, expr: &CallExpr) -> RuntimeResult {
+        let variable = self.environment.borrow().get(&expr.name);
+        let closure = match (variable, self.function(None, &expr.name)) {
+            (Some(Value::Function(closure)), _) | (None, Some(closure)) => closure,
+            (Some(value), _) => return Err(RuntimeError::at(&expr.token, format!(
+                "`{}` is not a function, found {}", expr.name, value.type_name()
+            )).into()),
+            (None, None) => {
+                let definition = self.definitions.borrow().structs.get(&expr.name).cloned();
+                return match definition {
+                    Some(definition) => self.construct(&definition, expr),
+                    None => self.call_builtin(expr),
+                };
+            },
+        };
+
+        if let Some((name, _)) = expr.named_arguments.first() {
+            return Err(RuntimeError::at(name, format!("Function `{}` does not take named arguments", expr.name)).into());
+        }
+
+        // arguments are evaluated in the scope of the caller
+        let mut arguments: Vec<Value> = vec![];
+        for argument in &expr.arguments {
+            arguments.push(self.execute_expr(argument.as_ref())?);
+        }
+
+        self.call_function(&closure, &expr.token, arguments)
+    }
+
+    fn accept_binary(&mut self, expr: &BinaryExpr) -> RuntimeResult {
+        let left = self.execute_expr(expr.left.as_ref())?;
+
+        // `and` and `or` only evaluate their right side if it decides the result
+        match expr.operator.token_type {
+            TokenType::AND | TokenType::OR => {
+                let left = left.is_truthy();
+                if left == (expr.operator.token_type == TokenType::OR) {
+                    return Ok(Value::Bool(left));
+                }
+
+                let right = self.execute_expr(expr.right.as_ref())?;
+                return Ok(Value::Bool(right.is_truthy()));
+            }
+            _ => {}
+        }
+
+        let right = self.execute_expr(expr.right.as_ref())?;
+        Ok(binary_operation(&expr.operator, left, right).map_err(|error| error.spanning(expr.span()))?)
+    }
+
+    fn accept_unary(&mut self, expr: &UnaryExpr) -> RuntimeResult {
+        let operand = self.execute_expr(expr.operand.as_ref())?;
+
+        match (expr.operator.token_type, operand) {
+            (TokenType::MINUS, Value::Int(value)) => value.checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::at(&expr.operator, String::from("Integer overflow in `-`")).spanning(expr.span()).into()),
+            (TokenType::MINUS, Value::Float(value)) => Ok(Value::Float(-value)),
+            (TokenType::NOT | TokenType::BANG, operand) => Ok(Value::Bool(!operand.is_truthy())),
+            (_, operand) => Err(RuntimeError::at(&expr.operator, format!(
+                "Unsupported operand type for `{}`: {}", expr.operator.lexeme, operand.type_name()
+            )).spanning(expr.span()).into()),
+        }
+    }
+
+    fn accept_if(&mut self, expr: &IfExpr) -> RuntimeResult {
+        let condition = self.execute_expr(expr.condition.as_ref())?;
+        let body = if condition.is_truthy() { &expr.body } else { &expr.else_body };
+
+        self.execute_block(body)?;
+        Ok(Value::Empty)
+    }
+
+    fn accept_while(&mut self, expr: &WhileExpr) -> RuntimeResult {
+        while self.execute_expr(expr.condition.as_ref())?.is_truthy() {
+            match self.execute_block(&expr.body) {
+                Ok(()) | Err(ControlFlow::Continue) => {}
+                Err(ControlFlow::Break) => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(Value::Empty)
+    }
+
+    fn accept_break(&mut self, _expr: &BreakExpr) -> RuntimeResult {
+        Err(ControlFlow::Break)
+    }
+
+    fn accept_continue(&mut self, _expr: &ContinueExpr) -> RuntimeResult {
+        Err(ControlFlow::Continue)
+    }
+
+    fn accept_return(&mut self, expr: &ReturnExpr) -> RuntimeResult {
+        let value = self.execute_expr(expr.value.as_ref())?;
+        Err(ControlFlow::Return(value))
+    }
+
+    fn accept_type(&mut self, _expr: &TypeExpr) -> RuntimeResult {
+        Ok(Value::Empty)
+    }
+
+    fn accept_var(&mut self, expr: &VarExpr) -> RuntimeResult {
+        let value = self.execute_expr(expr.value.as_ref())?;
+        self.define(&expr.token, value, true)?;
+
+        Ok(Value::Empty)
+    }
+
+    fn accept_assign(&mut self, expr: &AssignExpr) -> RuntimeResult {
+        match expr.target.kind() {
+            ExpressionKind::Variable(variable) => {
+                let mut value = self.execute_expr(expr.value.as_ref())?;
+                if expr.operator.token_type != TokenType::EQUAL {
+                    let current = self.execute_expr(expr.target.as_ref())?;
+                    value = compound_assignment(&expr.operator, current, value).map_err(|error| error.spanning(expr.span()))?;
+                }
+
+                match self.environment.borrow_mut().assign(&variable.name, value) {
+                    Ok(()) => Ok(Value::Empty),
+                    Err(AssignmentError::Undefined) => Err(RuntimeError::at(
+                        &variable.token, format!("Undefined variable `{}`", variable.name)
+                    ).into()),
+                    Err(AssignmentError::Constant) => Err(RuntimeError::at(
+                        &variable.token, format!("Cannot assign twice to constant `{}`", variable.name)
+                    ).into()),
+                }
+            },
+            ExpressionKind::Index(target) => {
+                // the list and the index are only evaluated once, even for `+=` and `-=`
+                let object = self.execute_expr(target.object.as_ref())?;
+                let index = self.execute_expr(target.index.as_ref())?;
+
+                let mut value = self.execute_expr(expr.value.as_ref())?;
+                if expr.operator.token_type != TokenType::EQUAL {
+                    let current = index_value(&target.token, &object, &index).map_err(|error| error.spanning(target.span))?;
+                    value = compound_assignment(&expr.operator, current, value).map_err(|error| error.spanning(expr.span()))?;
+                }
+
+                match &object {
+                    Value::List(elements) => {
+                        let position = list_position(&target.token, &index, elements.borrow().len()).map_err(|error| error.spanning(target.span))?;
+                        elements.borrow_mut()[position] = value;
+                    },
+                    Value::Map(entries) => {
+                        entries.borrow_mut().insert(map_key(&target.token, &index)?, value);
+                    },
+                    _ => return Err(RuntimeError::at(&target.token, format!(
+                        "Cannot assign to an element of {}", object.type_name()
+                    )).into())
+                }
+
+                Ok(Value::Empty)
+            },
+            ExpressionKind::Get(target) => {
+                let object = self.execute_expr(target.object.as_ref())?;
+
+                let mut value = self.execute_expr(expr.value.as_ref())?;
+                if expr.operator.token_type != TokenType::EQUAL {
+                    let current = field_value(target, &object)?;
+                    value = compound_assignment(&expr.operator, current, value).map_err(|error| error.spanning(expr.span()))?;
+                }
+
+                let instance = match &object {
+                    Value::Struct(instance) => instance,
+                    _ => return Err(RuntimeError::at(&target.token, format!(
+                        "Cannot assign to field `{}` of {}", target.name, object.type_name()
+                    )).into())
+                };
+
+                match instance.borrow_mut().fields.get_mut(&target.name) {
+                    Some(field) => *field = value,
+                    None => return Err(RuntimeError::at(&target.token, format!(
+                        "Struct `{}` has no field `{}`", object.type_name(), target.name
+                    )).into())
+                }
+
+                Ok(Value::Empty)
+            },
+            _ => Err(RuntimeError::at(&expr.operator, String::from("Invalid assignment target")).into())
+        }
+    }
+
+    fn accept_list(&mut self, expr: &ListExpr) -> RuntimeResult {
+        let mut elements: Vec<Value> = vec![];
+        for element in &expr.elements {
+            elements.push(self.execute_expr(element.as_ref())?);
+        }
+
+        Ok(Value::list(elements))
+    }
+
+    fn accept_index(&mut self, expr: &IndexExpr) -> RuntimeResult {
+        let object = self.execute_expr(expr.object.as_ref())?;
+        let index = self.execute_expr(expr.index.as_ref())?;
+
+        Ok(index_value(&expr.token, &object, &index).map_err(|error| error.spanning(expr.span))?)
+    }
+
+    /// Slices like Python does: bounds may be negative and are clamped to the length,
+    /// an empty range results in an empty list or string
+    fn accept_slice(&mut self, expr: &SliceExpr) -> RuntimeResult {
+        let object = self.execute_expr(expr.object.as_ref())?;
+        let start = self.execute_expr(expr.start.as_ref())?;
+        let end = self.execute_expr(expr.end.as_ref())?;
+
+        match &object {
+            Value::List(elements) => {
+                let elements = elements.borrow();
+                let (start, end) = slice_bounds(&expr.token, &start, &end, elements.len()).map_err(|error| error.spanning(expr.span))?;
+                Ok(Value::list(elements[start..end].to_vec()))
+            },
+            Value::Str(value) => {
+                let characters: Vec<char> = value.chars().collect();
+                let (start, end) = slice_bounds(&expr.token, &start, &end, characters.len()).map_err(|error| error.spanning(expr.span))?;
+                Ok(Value::Str(characters[start..end].iter().collect()))
+            },
+            _ => Err(RuntimeError::at(&expr.token, format!("Cannot slice {}", object.type_name())).spanning(expr.object.span()).into())
+        }
+    }
+
+    fn accept_for(&mut self, expr: &ForExpr) -> RuntimeResult {
+        let iterable = self.execute_expr(expr.iterable.as_ref())?;
+
+        // the loop walks over a snapshot, changing the list or map inside the body does not affect it
+        let entries: Vec<(Value, Value)> = match &iterable {
+            Value::List(elements) => elements.borrow().iter()
+                .enumerate()
+                .map(|(index, element)| (Value::Int(index as i64), element.clone()))
+                .collect(),
+            Value::Str(value) => value.chars()
+                .enumerate()
+                .map(|(index, character)| (Value::Int(index as i64), Value::Str(character.to_string())))
+                .collect(),
+            Value::Map(entries) => entries.borrow().iter()
+                .map(|(key, value)| (key.to_value(), value.clone()))
+                .collect(),
+            _ => return Err(RuntimeError::at(&expr.token, format!("Cannot iterate over {}", iterable.type_name())).spanning(expr.iterable.span()).into())
+        };
+
+        let is_map = matches!(iterable, Value::Map(..));
+
+        for (key, value) in entries {
+            let environment = Environment::with_parent(self.environment.clone());
+
+            match &expr.value_variable {
+                Some(value_variable) => {
+                    environment.borrow_mut().define(expr.variable.to_string(), key, false);
+                    environment.borrow_mut().define(value_variable.lexeme.to_string(), value, false);
+                },
+                // a single variable gets the keys of a map but the elements of a list
+                None => {
+                    let element = if is_map { key } else { value };
+                    environment.borrow_mut().define(expr.variable.to_string(), element, false);
+                }
+            }
+
+            match self.execute_scoped(&expr.body, environment) {
+                Ok(()) | Err(ControlFlow::Continue) => {}
+                Err(ControlFlow::Break) => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(Value::Empty)
+    }
+
+    /// Types are declared before the module runs
+    fn accept_struct(&mut self, _expr: &Rc<StructExpr>) -> RuntimeResult {
+        Ok(Value::Empty)
+    }
+
+    fn accept_get(&mut self, expr: &GetExpr) -> RuntimeResult {
+        if let Some(definition) = self.type_of(expr.object.as_ref()).and_then(|name| self.enum_definition(&name)) {
+            return self.construct_variant(&definition, &expr.token, vec![]);
+        }
+
+        let object = self.execute_expr(expr.object.as_ref())?;
+        if let Value::Module(module) = &object {
+            return Ok(self.export(module, &expr.token)?);
+        }
+
+        Ok(field_value(expr, &object)?)
+    }
+
+    fn accept_enum(&mut self, _expr: &Rc<EnumExpr>) -> RuntimeResult {
+        Ok(Value::Empty)
+    }
+
+    fn accept_method_call(&mut self, expr: &MethodCallExpr) -> RuntimeResult {
+        let type_name = self.type_of(expr.object.as_ref());
+        let object = match &type_name {
+            Some(..) => Value::Empty,
+            None => self.execute_expr(expr.object.as_ref())?
+        };
+
+        let mut arguments: Vec<Value> = vec![];
+        for argument in &expr.arguments {
+            arguments.push(self.execute_expr(argument.as_ref())?);
+        }
+
+        if let Some(type_name) = type_name {
+            return self.call_on_type(&type_name, expr, arguments);
+        }
+
+        if let Value::Module(module) = &object {
+            return match self.export(module, &expr.token)? {
+                Value::Function(closure) => self.call_function(&closure, &expr.token, arguments),
+                other => Err(RuntimeError::at(&expr.token, format!(
+                    "`{}.{}` is not a function, found {}", module.name, expr.name, other.type_name()
+                )).into())
+            };
+        }
+
+        match self.method(&object.type_name(), &expr.name) {
+            Some(closure) if closure.function.is_method() => {
+                arguments.insert(0, object);
+                self.call_function(&closure, &expr.token, arguments)
+            },
+            Some(closure) => Err(RuntimeError::at(&expr.token, format!(
+                "`{}` has no `self` parameter and has to be called on the type", closure.function.qualified_name()
+            )).into()),
+            // a struct field holding a function is called like a method, without passing the instance
+            None => match field_function(&object, &expr.name) {
+                Some(closure) => self.call_function(&closure, &expr.token, arguments),
+                None => Err(RuntimeError::at(&expr.token, format!(
+                    "No method `{}` on {}", expr.name, object.type_name()
+                )).into())
+            }
+        }
+    }
+
+    fn accept_lambda(&mut self, expr: &LambdaExpr) -> RuntimeResult {
+        Ok(Value::Function(self.closure(&expr.function)))
+    }
+
+    fn accept_apply(&mut self, expr: &ApplyExpr) -> RuntimeResult {
+        let callee = self.execute_expr(expr.callee.as_ref())?;
+
+        let mut arguments: Vec<Value> = vec![];
+        for argument in &expr.arguments {
+            arguments.push(self.execute_expr(argument.as_ref())?);
+        }
+
+        match callee {
+            Value::Function(closure) => self.call_function(&closure, &expr.token, arguments),
+            other => Err(RuntimeError::at(&expr.token, format!("Cannot call a value of type {}", other.type_name())).into())
+        }
+    }
+
+    /// Binds the module or the listed exports of a module, which ran before this one
+    fn accept_import(&mut self, expr: &ImportExpr) -> RuntimeResult {
+        let module = modules::module_key(&expr.module.file_name, &expr.module.lexeme)
+            .and_then(|key| self.modules.get(&key).cloned());
+        let module = match module {
+            Some(module) => module,
+            None => return Err(RuntimeError::at(&expr.module, format!("Module `{}` is not loaded", expr.module.lexeme)).into())
+        };
+
+        if expr.names.is_empty() {
+            self.define(&expr.module, Value::Module(module.clone()), false)?;
+        }
+
+        for name in &expr.names {
+            let value = self.export(&module, name)?;
+            self.define(name, value, false)?;
+        }
+
+        Ok(Value::Empty)
+    }
+
+    /// The functions of an impl block are declared before the module runs
+    fn accept_impl(&mut self, _expr: &ImplExpr) -> RuntimeResult {
+        Ok(Value::Empty)
+    }
+
+    /// Tries the arms from top to bottom, each one in its own scope holding the bindings of its pattern
+    fn accept_match(&mut self, expr: &MatchExpr) -> RuntimeResult {
+        let subject = self.execute_expr(expr.subject.as_ref())?;
+
+        for arm in &expr.arms {
+            let environment = Environment::with_parent(self.environment.clone());
+            let surrounding_environment = std::mem::replace(&mut self.environment, environment);
+            let result = self.execute_arm(arm, &subject);
+            self.environment = surrounding_environment;
+
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+
+        Err(RuntimeError::at(&expr.keyword, format!("Non-exhaustive match: no arm matches {}", subject.nested_string())).spanning(expr.span()).into())
+    }
+
+    fn accept_map(&mut self, expr: &MapExpr) -> RuntimeResult {
+        let mut entries = IndexMap::new();
+        for (key_expr, value_expr) in &expr.entries {
+            let key = self.execute_expr(key_expr.as_ref())?;
+            let value = self.execute_expr(value_expr.as_ref())?;
+            entries.insert(map_key(&expr.token, &key)?, value);
+        }
+
+        Ok(Value::map(entries))
+    }
+}
+
+fn field_value(expr: &GetExpr, object: &Value) -> Result<Value, RuntimeError> {
+    let instance = match object {
+        Value::Struct(instance) => instance.borrow(),
+        _ => return Err(RuntimeError::at(&expr.token, format!("Cannot access field `{}` of {}", expr.name, object.type_name())))
+    };
+
+    match instance.fields.get(&expr.name) {
+        Some(value) => Ok(value.clone()),
+        None => Err(RuntimeError::at(&expr.token, format!("Struct `{}` has no field `{}`", instance.name, expr.name)))
+    }
+}
+
+/// Applies the operator of `+=` and `-=` to the current value, `=` just takes the new value
+fn compound_assignment(operator: &Token, current: Value, value: Value) -> Result<Value, RuntimeError> {
+    let token_type = match operator.token_type {
+        TokenType::PlusEqual => TokenType::PLUS,
+        TokenType::MinusEqual => TokenType::MINUS,
+        _ => return Ok(value)
+    };
+
+    binary_operation(&Token { token_type, ..operator.clone() }, current, value)
+}
+
+fn index_value(token: &Token, object: &Value, index: &Value) -> Result<Value, RuntimeError> {
+    match object {
+        Value::List(elements) => {
+            let elements = elements.borrow();
+            let position = list_position(token, index, elements.len())?;
+            Ok(elements[position].clone())
+        },
+        Value::Str(value) => {
+            let characters: Vec<char> = value.chars().collect();
+            let position = list_position(token, index, characters.len())?;
+            Ok(Value::Str(characters[position].to_string()))
+        },
+        Value::Map(entries) => match entries.borrow().get(&map_key(token, index)?) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::at(token, format!("Key {} not found in map", index.nested_string())))
+        },
+        _ => Err(RuntimeError::at(token, format!("Cannot index into {}", object.type_name())))
+    }
+}
+
+fn map_key(token: &Token, key: &Value) -> Result<MapKey, RuntimeError> {
+    MapKey::from_value(key)
+        .ok_or_else(|| RuntimeError::at(token, format!("A {} cannot be used as map key", key.type_name())))
+}
+
+/// Position of an index in a sequence of the given length, negative indices count from the end
+fn list_position(token: &Token, index: &Value, length: usize) -> Result<usize, RuntimeError> {
+    let index = match index {
+        Value::Int(index) => *index,
+        _ => return Err(RuntimeError::at(token, format!("Index has to be an int, found {}", index.type_name())))
+    };
+
+    let position = if index < 0 { index + length as i64 } else { index };
+    if position < 0 || position >= length as i64 {
+        return Err(RuntimeError::at(token, format!("Index {} out of range for length {}", index, length)));
+    }
+
+    Ok(position as usize)
+}
+
+fn slice_bounds(token: &Token, start: &Value, end: &Value, length: usize) -> Result<(usize, usize), RuntimeError> {
+    let bound = |value: &Value, default: usize| match value {
+        Value::Empty => Ok(default),
+        Value::Int(bound) => {
+            let bound = if *bound < 0 { bound + length as i64 } else { *bound };
+            Ok(bound.clamp(0, length as i64) as usize)
+        },
+        _ => Err(RuntimeError::at(token, format!("Slice bounds have to be ints, found {}", value.type_name())))
+    };
+
+    let start = bound(start, 0)?;
+    let end = bound(end, length)?;
+
+    Ok((start, end.max(start)))
+}
+
+/// Evaluates every binary operator except the short-circuiting `and` and `or`.
+///
+/// Two ints stay an int, as soon as one side is a float the other one is promoted.
+/// `+` with a string on either side concatenates the printed form of both values,
+/// `+` on two lists creates a new list holding the elements of both.
+/// `/` on two ints truncates towards zero (`7 / 2 == 3`, `-7 / 2 == -3`) and `%` keeps
+/// the sign of the left side, `7 / 2.0 == 3.5`. Dividing by zero is an error for ints
+/// and floats alike, int overflow is an error instead of wrapping around.
+fn binary_operation(operator: &Token, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let unsupported = |left: &Value, right: &Value| RuntimeError::at(operator, format!(
+        "Unsupported operand types for `{}`: {} and {}", operator.lexeme, left.type_name(), right.type_name()
+    ));
+
+    match operator.token_type {
+        TokenType::EqualEqual => return Ok(Value::Bool(values_equal(&left, &right))),
+        TokenType::BangEqual => return Ok(Value::Bool(!values_equal(&left, &right))),
+        _ => {}
+    }
+
+    match (&left, &right) {
+        (Value::Int(left), Value::Int(right)) => int_operation(operator, *left, *right),
+        (Value::Int(..) | Value::Float(..), Value::Int(..) | Value::Float(..)) => {
+            let is_division = matches!(operator.token_type, TokenType::SLASH | TokenType::PERCENT);
+            if is_division && as_float(&right) == 0.0 {
+                return Err(RuntimeError::at(operator, String::from("Division by zero")));
+            }
+
+            float_operation(operator, as_float(&left), as_float(&right)).ok_or_else(|| unsupported(&left, &right))
+        }
+        (Value::List(left_elements), Value::List(right_elements)) if operator.token_type == TokenType::PLUS => {
+            let mut elements = left_elements.borrow().clone();
+            elements.extend(right_elements.borrow().iter().cloned());
+            Ok(Value::list(elements))
+        },
+        (Value::Str(..), _) | (_, Value::Str(..)) if operator.token_type == TokenType::PLUS => {
+            Ok(Value::Str(format!("{}{}", left, right)))
+        },
+        (Value::Str(left_value), Value::Str(right_value)) => match operator.token_type {
+            TokenType::LESS => Ok(Value::Bool(left_value < right_value)),
+            TokenType::LessEqual => Ok(Value::Bool(left_value <= right_value)),
+            TokenType::GREATER => Ok(Value::Bool(left_value > right_value)),
+            TokenType::GreaterEqual => Ok(Value::Bool(left_value >= right_value)),
+            _ => Err(unsupported(&left, &right))
+        },
+        _ => Err(unsupported(&left, &right))
+    }
+}
+
+fn int_operation(operator: &Token, left: i64, right: i64) -> Result<Value, RuntimeError> {
+    let result = match operator.token_type {
+        TokenType::PLUS => left.checked_add(right),
+        TokenType::MINUS => left.checked_sub(right),
+        TokenType::STAR => left.checked_mul(right),
+        TokenType::SLASH | TokenType::PERCENT if right == 0 => {
+            return Err(RuntimeError::at(operator, String::from("Division by zero")))
+        },
+        TokenType::SLASH => left.checked_div(right),
+        TokenType::PERCENT => left.checked_rem(right),
+        TokenType::LESS => return Ok(Value::Bool(left < right)),
+        TokenType::LessEqual => return Ok(Value::Bool(left <= right)),
+        TokenType::GREATER => return Ok(Value::Bool(left > right)),
+        TokenType::GreaterEqual => return Ok(Value::Bool(left >= right)),
+        _ => return Err(RuntimeError::at(operator, format!("Unsupported operand types for `{}`: int and int", operator.lexeme)))
+    };
+
+    result.map(Value::Int)
+        .ok_or_else(|| RuntimeError::at(operator, format!("Integer overflow in `{}`", operator.lexeme)))
+}
+
+fn float_operation(operator: &Token, left: f64, right: f64) -> Option<Value> {
+    let value = match operator.token_type {
+        TokenType::PLUS => Value::Float(left + right),
+        TokenType::MINUS => Value::Float(left - right),
+        TokenType::STAR => Value::Float(left * right),
+        TokenType::SLASH => Value::Float(left / right),
+        TokenType::PERCENT => Value::Float(left % right),
+        TokenType::LESS => Value::Bool(left < right),
+        TokenType::LessEqual => Value::Bool(left <= right),
+        TokenType::GREATER => Value::Bool(left > right),
+        TokenType::GreaterEqual => Value::Bool(left >= right),
+        _ => return None
+    };
+
+    Some(value)
+}
+
+fn as_float(value: &Value) -> f64 {
+    match value {
+        Value::Int(value) => *value as f64,
+        Value::Float(value) => *value,
+        _ => f64::NAN
+    }
+}
+
+/// Function stored in the field `name` of a struct instance
+fn field_function(object: &Value, name: &str) -> Option<Rc<Closure>> {
+    match object {
+        Value::Struct(instance) => match instance.borrow().fields.get(name) {
+            Some(Value::Function(closure)) => Some(closure.clone()),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Structural equality. Lists, maps and structs are equal to themselves without looking
+/// inside, a pair compared again further down a structure containing itself counts as equal.
+fn values_equal(left: &Value, right: &Value) -> bool {
+    equal(left, right, &mut vec![])
+}
+
+/// `comparing` holds the pairs of lists, maps and structs compared around these values
+fn equal(left: &Value, right: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+    let pair = match (left, right) {
+        (Value::List(left), Value::List(right)) => (Rc::as_ptr(left) as *const (), Rc::as_ptr(right) as *const ()),
+        (Value::Map(left), Value::Map(right)) => (Rc::as_ptr(left) as *const (), Rc::as_ptr(right) as *const ()),
+        (Value::Struct(left), Value::Struct(right)) => (Rc::as_ptr(left) as *const (), Rc::as_ptr(right) as *const ()),
+        _ => return contents_equal(left, right, comparing)
+    };
+
+    if pair.0 == pair.1 || comparing.contains(&pair) {
+        return true;
+    }
+
+    comparing.push(pair);
+    let result = contents_equal(left, right, comparing);
+    comparing.pop();
+
+    result
+}
+
+fn contents_equal(left: &Value, right: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+    match (left, right) {
+        (Value::Int(..), Value::Float(..)) | (Value::Float(..), Value::Int(..)) => as_float(left) == as_float(right),
+        (Value::List(left_elements), Value::List(right_elements)) => {
+            let (left_elements, right_elements) = (left_elements.borrow(), right_elements.borrow());
+            left_elements.len() == right_elements.len()
+                && left_elements.iter().zip(right_elements.iter()).all(|(left, right)| equal(left, right, comparing))
+        },
+        (Value::Map(left_entries), Value::Map(right_entries)) => {
+            let (left_entries, right_entries) = (left_entries.borrow(), right_entries.borrow());
+            left_entries.len() == right_entries.len() && left_entries.iter().all(|(key, left)| {
+                right_entries.get(key).is_some_and(|right| equal(left, right, comparing))
+            })
+        },
+        (Value::Struct(left_instance), Value::Struct(right_instance)) => {
+            let (left_instance, right_instance) = (left_instance.borrow(), right_instance.borrow());
+            left_instance.name == right_instance.name && left_instance.fields.iter()
+                .zip(right_instance.fields.values())
+                .all(|((_, left), right)| equal(left, right, comparing))
+        },
+        (Value::Enum(left_value), Value::Enum(right_value)) => {
+            left_value.enum_name == right_value.enum_name
+                && left_value.variant == right_value.variant
+                && left_value.values.iter().zip(&right_value.values).all(|(left, right)| equal(left, right, comparing))
+        },
+        // exports may hold lists containing themselves, a module is only equal to itself
+        (Value::Module(left_module), Value::Module(right_module)) => Rc::ptr_eq(left_module, right_module),
+        _ => left == right
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(start: Value, end: Value, length: usize) -> Option<(usize, usize)> {
+        let token = Token::new(TokenType::LeftBracket, String::from("["), String::from("main.hunt"), Span::default());
+        slice_bounds(&token, &start, &end, length).ok()
+    }
+
+    fn trace(functions: &[&str]) -> Vec<String> {
+        let mut error = RuntimeError::new(String::from("Stack overflow"));
+        error.trace = functions.iter().map(|function| function.to_string()).collect();
+        error.trace_lines()
+    }
+
+    #[test]
+    fn slice_bounds_default_to_the_whole_sequence() {
+        assert_eq!(bounds(Value::Empty, Value::Empty, 5), Some((0, 5)));
+        assert_eq!(bounds(Value::Int(1), Value::Int(3), 5), Some((1, 3)));
+    }
+
+    #[test]
+    fn slice_bounds_count_negative_bounds_from_the_end() {
+        assert_eq!(bounds(Value::Int(-2), Value::Empty, 5), Some((3, 5)));
+        assert_eq!(bounds(Value::Empty, Value::Int(-1), 5), Some((0, 4)));
+        assert_eq!(bounds(Value::Int(-9), Value::Int(-7), 5), Some((0, 0)));
+    }
+
+    #[test]
+    fn slice_bounds_clamp_out_of_range_bounds() {
+        assert_eq!(bounds(Value::Int(2), Value::Int(99), 5), Some((2, 5)));
+        assert_eq!(bounds(Value::Int(7), Value::Empty, 5), Some((5, 5)));
+        assert_eq!(bounds(Value::Int(4), Value::Int(1), 5), Some((4, 4)));
+        assert_eq!(bounds(Value::Empty, Value::Empty, 0), Some((0, 0)));
+    }
+
+    #[test]
+    fn slice_bounds_have_to_be_ints() {
+        assert_eq!(bounds(Value::Float(1.0), Value::Empty, 5), None);
+        assert_eq!(bounds(Value::Empty, Value::Str(String::from("2")), 5), None);
+    }
+
+    #[test]
+    fn trace_lines_merge_recursion() {
+        assert_eq!(trace(&["f", "f", "f", "main"]), ["in `f` (3 times)", "in `main`"]);
+        assert_eq!(trace(&["f", "g", "h"]), ["in `f`", "in `g`", "in `h`"]);
+        assert!(trace(&[]).is_empty());
+    }
+
+    #[test]
+    fn trace_lines_merge_mutual_recursion() {
+        let mut functions = ["even", "odd"].repeat(127);
+        functions.extend(["even", "start"]);
+
+        assert_eq!(trace(&functions), ["in `even`, `odd` (127 times)", "in `even`", "in `start`"]);
+    }
+
+    #[test]
+    fn trace_lines_cut_off_long_traces() {
+        let functions: Vec<String> = (0..20).map(|index| format!("f{}", index)).collect();
+        let functions: Vec<&str> = functions.iter().map(String::as_str).collect();
+        let lines = trace(&functions);
+
+        assert_eq!(lines.len(), MAX_TRACE_LINES + 1);
+        assert_eq!(lines[MAX_TRACE_LINES - 1], "in `f15`");
+        assert_eq!(lines[MAX_TRACE_LINES], "... 4 more");
+    }
+}
@@ -1,4 +1,7 @@
 use std::fmt::{Debug, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::codes::Code;
 use crate::reporter::CodeReporter;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -9,34 +12,69 @@ pub enum TokenType {
     COMMENT, LineBreak, SPACE, SpaceLevel,
 
     // Single-character tokens.
-    COMMA, DOT, LeftParen, RightParen,
-    MINUS, PLUS,
+    COMMA, DOT, COLON, LeftParen, RightParen, LeftBracket, RightBracket, LeftBrace, RightBrace,
+    MINUS, PLUS, STAR, SLASH, PERCENT,
 
     // One or two character tokens.
     EQUAL, BANG, BangEqual, EqualEqual,
+    PlusEqual, MinusEqual,
+    LESS, LessEqual, GREATER, GreaterEqual,
+    ARROW,
 
     // Literals.
     IDENTIFIER, STRING, INT, FLOAT,
 
     // Keywords.
-    IF, ELSE, WHILE,
-    CONST, FUNCTION,
+    IF, ELSE, WHILE, FOR, IN, BREAK, CONTINUE,
+    CONST, VAR, FUNCTION, RETURN, STRUCT, ENUM, MATCH, IMPL,
+    IMPORT, FROM,
     PRINT,
     TRUE, FALSE,
+    AND, OR, NOT,
 
     EOF,
 }
 
+/// Location of a token or expression in its file. Offsets are byte offsets into the
+/// source, lines and columns count characters starting at 1 and the end is exclusive.
+/// A column of 0 means that only the line is known.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Span from the start of this span to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        // expressions without a location, like a missing else branch, do not extend a span
+        if other == Span::default() {
+            return self;
+        }
+
+        if self == Span::default() {
+            return other;
+        }
+
+        Span { end: other.end, end_line: other.end_line, end_column: other.end_column, ..self }
+    }
+}
+
+#[derive(Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub file_name: String,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, file_name: String, line: usize) -> Self {
-        Token { token_type, lexeme, file_name, line }
+    pub fn new(token_type: TokenType, lexeme: String, file_name: String, span: Span) -> Self {
+        Token { token_type, lexeme, file_name, span }
     }
 }
 
@@ -47,14 +85,29 @@ impl Debug for Token {
             TokenType::INVALID => "Invalid token",
             TokenType::COMMA => ",",
             TokenType::DOT => ".",
+            TokenType::COLON => ":",
             TokenType::LeftParen => "(",
             TokenType::RightParen => ")",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
             TokenType::MINUS => "-",
             TokenType::PLUS => "+",
+            TokenType::STAR => "*",
+            TokenType::SLASH => "/",
+            TokenType::PERCENT => "%",
             TokenType::EQUAL => "=",
             TokenType::BANG => "!",
             TokenType::BangEqual => "!=",
             TokenType::EqualEqual => "==",
+            TokenType::PlusEqual => "+=",
+            TokenType::MinusEqual => "-=",
+            TokenType::LESS => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::GREATER => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::ARROW => "->",
             TokenType::IDENTIFIER => {
                 format_value = format!("Identifier (\"{}\")", &self.lexeme);
                 format_value.as_str()
@@ -74,8 +127,26 @@ impl Debug for Token {
             TokenType::TRUE => "boolean: true",
             TokenType::FALSE => "boolean: false",
             TokenType::CONST => "keyword: const",
+            TokenType::VAR => "keyword: var",
             TokenType::FUNCTION => "keyword: fun",
+            TokenType::RETURN => "keyword: return",
+            TokenType::STRUCT => "keyword: struct",
+            TokenType::ENUM => "keyword: enum",
+            TokenType::MATCH => "keyword: match",
+            TokenType::IMPL => "keyword: impl",
+            TokenType::IMPORT => "keyword: import",
+            TokenType::FROM => "keyword: from",
             TokenType::PRINT => "keyword: print",
+            TokenType::IF => "keyword: if",
+            TokenType::ELSE => "keyword: else",
+            TokenType::WHILE => "keyword: while",
+            TokenType::FOR => "keyword: for",
+            TokenType::IN => "keyword: in",
+            TokenType::BREAK => "keyword: break",
+            TokenType::CONTINUE => "keyword: continue",
+            TokenType::AND => "keyword: and",
+            TokenType::OR => "keyword: or",
+            TokenType::NOT => "keyword: not",
             TokenType::SpaceLevel => ">",
             TokenType::SPACE => "<SPACE>",
             TokenType::LineBreak => "<BR>",
@@ -92,32 +163,47 @@ impl Debug for Token {
 pub struct Scanner {
     reporter: CodeReporter,
 
-    source: String,
+    source: Vec<char>,
+    /// Byte offset of every character of the source and of its end
+    byte_offsets: Vec<usize>,
     file_name: String,
+    /// Content of the last scanned string literal with its escapes resolved
+    string_value: String,
 
     start: usize,
     current: usize,
     line: usize,
+    /// Index of the first character of the current line
+    line_start: usize,
+    /// Line and column where the current token starts
+    start_line: usize,
+    start_column: usize,
 }
 
 impl Scanner {
     pub fn new(reporter: CodeReporter) -> Self {
         Scanner {
-            reporter: reporter,
+            reporter,
 
-            source: String::new(),
+            source: vec![],
+            byte_offsets: vec![],
             file_name: String::new(),
+            string_value: String::new(),
 
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
         }
     }
 
     pub fn scan_string(&mut self, file_name: String, input: String) -> Vec<Token> {
 
         self.file_name = file_name;
-        self.source = input;
+        self.source = input.chars().collect();
+        self.byte_offsets = input.char_indices().map(|(offset, _)| offset).chain([input.len()]).collect();
 
         let mut tokens = vec![];
 
@@ -125,52 +211,82 @@ impl Scanner {
             self.start = self.current;
             let token = self.scan_token();
 
+            // indentation only counts at the beginning of a line
+            let is_line_start = matches!(
+                tokens.last(),
+                None | Some(Token { token_type: TokenType::LineBreak | TokenType::SpaceLevel, .. })
+            );
+
             match token.token_type {
                 TokenType::COMMENT => {}
                 TokenType::SPACE => {}
-                TokenType::LineBreak => { self.line += 1; tokens.push(token); }
+                TokenType::SpaceLevel if !is_line_start => {}
+                TokenType::LineBreak => { self.new_line(); tokens.push(token); }
                 _ => tokens.push(token)
             }
 
         }
 
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.current - self.line_start + 1;
         tokens.push(self.create_token(TokenType::EOF));
 
         return tokens;
     }
 
     fn is_at_end(&self) -> bool {
-        return self.current >= self.source.len() as usize
+        return self.current >= self.source.len()
     }
 
     fn scan_token(&mut self) -> Token {
         self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.current - self.line_start + 1;
         let character = self.advance();
 
         let token_type = match character {
             '(' => TokenType::LeftParen,
             ')' => TokenType::RightParen,
+            '[' => TokenType::LeftBracket,
+            ']' => TokenType::RightBracket,
+            '{' => TokenType::LeftBrace,
+            '}' => TokenType::RightBrace,
             ',' => TokenType::COMMA,
             '.' => TokenType::DOT,
-            '+' => TokenType::PLUS,
-            '-' => TokenType::MINUS,
+            ':' => TokenType::COLON,
+            '+' => if self.matches_character('=') { TokenType::PlusEqual } else { TokenType::PLUS },
+            '-' => {
+                if self.matches_character('>') { TokenType::ARROW }
+                else if self.matches_character('=') { TokenType::MinusEqual }
+                else { TokenType::MINUS }
+            },
+            '*' => TokenType::STAR,
+            '/' => TokenType::SLASH,
+            '%' => TokenType::PERCENT,
             '!' => if self.matches_character('=') { TokenType::BangEqual } else { TokenType::BANG },
             '=' => if self.matches_character('=') { TokenType::EqualEqual } else { TokenType::EQUAL },
-            '#' => { while self.peek() != '\n' { self.advance(); } TokenType::COMMENT },
+            '<' => if self.matches_character('=') { TokenType::LessEqual } else { TokenType::LESS },
+            '>' => if self.matches_character('=') { TokenType::GreaterEqual } else { TokenType::GREATER },
+            '#' => { while self.peek() != '\n' && !self.is_at_end() { self.advance(); } TokenType::COMMENT },
             '\r' => TokenType::SPACE,
             '\n' => TokenType::LineBreak,
             '\t' => TokenType::SpaceLevel,
             ' ' => {
                 if self.matches_character(' ') { TokenType::SpaceLevel } else { TokenType::SPACE }
             },
-            '"' => self.scan_string_token(),
+            '"' => self.scan_string_token(false),
             _   => {
 
-                if character.is_numeric() {
+                if character == 'r' && self.matches_character('"') {
+                    self.scan_string_token(true)
+                } else if character.is_numeric() {
                     self.scan_number()
-                } else if character.is_alphabetic() {
+                } else if character.is_alphabetic() || character == '_' {
                     self.scan_identifier()
                 } else {
+                    let message = format!("Unexpected character `{}`", character);
+                    self.reporter.report_error(Code::SyntaxError, &self.file_name, self.current_span(), &message);
                     TokenType::INVALID
                 }
 
@@ -180,6 +296,48 @@ impl Scanner {
         return self.create_token(token_type)
     }
 
+    /// Moves to the next line after a line break was consumed
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    /// Span of the current token, multi-line strings end on a later line than they start
+    fn current_span(&self) -> Span {
+        Span {
+            start: self.byte_offsets[self.start],
+            end: self.byte_offsets[self.current],
+            line: self.start_line,
+            column: self.start_column,
+            end_line: self.line,
+            end_column: self.current - self.line_start + 1,
+        }
+    }
+
+    /// Span of the character consumed last
+    fn previous_character_span(&self) -> Span {
+        let column = self.current - self.line_start;
+        Span {
+            start: self.byte_offsets[self.current - 1],
+            end: self.byte_offsets[self.current],
+            line: self.line,
+            column,
+            end_line: self.line,
+            end_column: column + 1,
+        }
+    }
+
+    /// Span for a problem on `line` inside the current token, the whole token if it
+    /// spans a single line and otherwise only the line
+    fn line_span(&self, line: usize) -> Span {
+        let span = self.current_span();
+        if span.line == span.end_line {
+            return span;
+        }
+
+        Span { line, column: 0, end_line: line, end_column: 0, ..span }
+    }
+
     fn scan_number(&mut self) -> TokenType {
         let mut token_type = TokenType::INT;
 
@@ -196,7 +354,7 @@ impl Scanner {
     }
 
     fn scan_identifier(&mut self) -> TokenType {
-        while self.peek().is_alphanumeric() {
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
@@ -204,90 +362,326 @@ impl Scanner {
 
         match token_str.as_str() {
             "const" => TokenType::CONST,
+            "var"   => TokenType::VAR,
             "fun"   => TokenType::FUNCTION,
+            "return" => TokenType::RETURN,
+            "struct" => TokenType::STRUCT,
+            "enum"  => TokenType::ENUM,
+            "match" => TokenType::MATCH,
+            "impl"  => TokenType::IMPL,
+            "import" => TokenType::IMPORT,
+            "from"  => TokenType::FROM,
             "print" => TokenType::PRINT,
             "if"    => TokenType::IF,
             "else"  => TokenType::ELSE,
             "while" => TokenType::WHILE,
+            "for"   => TokenType::FOR,
+            "in"    => TokenType::IN,
+            "break" => TokenType::BREAK,
+            "continue" => TokenType::CONTINUE,
             "true"  => TokenType::TRUE,
             "false" => TokenType::FALSE,
+            "and"   => TokenType::AND,
+            "or"    => TokenType::OR,
+            "not"   => TokenType::NOT,
             _ => TokenType::IDENTIFIER
         }
     }
 
-    fn scan_string_token(&mut self) -> TokenType {
-        while self.peek() != '"' && !self.is_at_end() {
+    /// Scans a string literal after its opening `"`, with `raw` set for `r"..."`.
+    ///
+    /// A `"""` opening starts a multi-line string, which gets its common indentation
+    /// stripped. Escape sequences are resolved into `string_value`, raw strings keep
+    /// their content as written.
+    ///
+    /// The resulting lexeme is a template for the parser: `{...}` embeds an expression,
+    /// while `{{` and `}}` stand for literal braces. Braces written as escapes or inside
+    /// raw strings are therefore doubled here, so `"\{"` and `"{{"` both end up as a literal `{`.
+    fn scan_string_token(&mut self, raw: bool) -> TokenType {
+        let multi_line = self.peek() == '"' && self.double_peek() == '"';
+        if multi_line {
+            self.advance();
+            self.advance();
+        }
 
-            if self.peek() == '\n' {
-                self.reporter.report_error(&self.file_name, self.line, &String::from("Broken string"));
+        let mut body = String::new();
+        loop {
+            if self.is_at_end() || (!multi_line && self.peek() == '\n') {
+                self.reporter.report_error(Code::InvalidString, &self.file_name, self.current_span(), "Broken string");
                 return TokenType::INVALID
             }
 
-            self.advance();
+            let character = self.advance();
+            if character == '"' && !multi_line {
+                break
+            }
+
+            if character == '"' && self.peek() == '"' && self.double_peek() == '"' {
+                self.advance();
+                self.advance();
+                break
+            }
+
+            // a line break cannot be escaped to continue a single-line string on the next line
+            if character == '\\' && !raw && !multi_line && matches!(self.peek(), '\n' | '\r') {
+                self.reporter.report_error(Code::InvalidString, &self.file_name, self.previous_character_span(), "Broken string");
+                return TokenType::INVALID
+            }
+
+            // an escaped character never closes the string
+            if character == '\\' && !raw && !self.is_at_end() {
+                body.push(character);
+                body.push(self.advance());
+            } else {
+                body.push(character);
+            }
+
+            if body.ends_with('\n') {
+                self.new_line();
+            }
         }
 
-        if self.is_at_end() {
-            self.reporter.report_error(&self.file_name, self.line, &String::from("Broken string"));
-            return TokenType::INVALID
+        let mut first_line = self.start_line;
+        if multi_line {
+            body = body.replace("\r\n", "\n");
+            if body.starts_with('\n') {
+                first_line += 1;
+            }
+            body = strip_indentation(&body);
         }
 
-        // The closing "
-        self.advance();
+        self.string_value = if raw {
+            body.replace('{', "{{").replace('}', "}}")
+        } else {
+            self.resolve_escapes(&body, first_line)
+        };
 
         return TokenType::STRING
     }
 
-    fn create_token(&self, token_type: TokenType) -> Token {
-        let mut range_beginning = self.start;
-        let mut range_ending = self.current;
-
-        if token_type == TokenType::STRING {
-            range_beginning = range_beginning + 1;
-            range_ending = range_ending - 1;
+    /// Resolves the escape sequences of a string body starting on `line`
+    fn resolve_escapes(&mut self, body: &str, mut line: usize) -> String {
+        let mut value = String::new();
+        let mut characters = body.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            match character {
+                // the line break stays in the string and is counted below
+                '\\' if characters.peek() == Some(&'\n') => {
+                    self.reporter.report_error(Code::InvalidString, &self.file_name, self.line_span(line), "A line break cannot be escaped");
+                },
+                '\\' => {
+                    if let Some(escaped) = self.scan_escape_sequence(&mut characters, line) {
+                        if escaped == '{' || escaped == '}' {
+                            value.push(escaped);
+                        }
+
+                        value.push(escaped);
+                    }
+                },
+                '\n' => {
+                    line += 1;
+                    value.push(character);
+                },
+                _ => value.push(character)
+            }
         }
 
+        value
+    }
+
+    fn scan_escape_sequence(&mut self, characters: &mut Peekable<Chars>, line: usize) -> Option<char> {
+        let escaped = match characters.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '"' => '"',
+            '\\' => '\\',
+            '{' => '{',
+            '}' => '}',
+            'u' => match scan_unicode_escape(characters) {
+                Some(character) => character,
+                None => {
+                    self.reporter.report_error(Code::InvalidString, &self.file_name, self.line_span(line), "Invalid unicode escape, expected \\u{XXXX}");
+                    return None
+                }
+            },
+            other => {
+                let message = format!("Unknown escape sequence `\\{}`", other.escape_debug());
+                self.reporter.report_error(Code::InvalidString, &self.file_name, self.line_span(line), &message);
+                return None
+            }
+        };
+
+        Some(escaped)
+    }
+
+    fn create_token(&self, token_type: TokenType) -> Token {
+        let lexeme = if token_type == TokenType::STRING {
+            self.string_value.clone()
+        } else {
+            self.get_current_token_string()
+        };
+
         Token::new(
             token_type,
-            self.source[range_beginning..range_ending].to_string(),
+            lexeme,
             self.file_name.clone(),
-            self.line
+            self.current_span()
         )
     }
 
     fn get_current_token_string(&self) -> String {
-        return self.source[self.start..self.current].to_string()
+        return self.source[self.start..self.current].iter().collect()
     }
 
     fn advance(&mut self) -> char {
-        let character = self.source.chars().nth(self.current as usize).unwrap();
+        let character = self.source[self.current];
         self.current += 1;
         return character;
     }
 
     fn peek(&mut self) -> char {
         if self.is_at_end() { return '\0' }
-        return self.source.chars().nth(self.current).unwrap();
+        return self.source[self.current];
     }
 
     fn double_peek(&mut self) -> char {
-        if self.is_at_end() { return '\0' }
-        return self.source.chars().nth(self.current+1).unwrap();
+        if self.current + 1 >= self.source.len() { return '\0' }
+        return self.source[self.current + 1];
     }
 
+    #[allow(dead_code)]
     fn look_back(&mut self) -> char {
         if self.is_at_end() { return '\0' }
         if self.current == 0 { return '\0' }
         let previous_index = self.current - 1;
-        return self.source.chars().nth(previous_index).unwrap();
+        return self.source[previous_index];
     }
 
     fn matches_character(&mut self, expected: char) -> bool {
         if self.is_at_end() { return false }
-        let character = self.source.chars().nth(self.current as usize).unwrap();
+        let character = self.source[self.current];
         if character != expected { return false }
 
         self.current += 1;
 
         return true
     }
-}
\ No newline at end of file
+}
+
+/// Scans the `{XXXX}` part of a `\u{XXXX}` escape with one to six hex digits
+fn scan_unicode_escape(characters: &mut Peekable<Chars>) -> Option<char> {
+    if characters.next() != Some('{') {
+        return None
+    }
+
+    let mut digits = String::new();
+    while let Some(digit) = characters.next_if(|character| character.is_ascii_hexdigit()) {
+        digits.push(digit);
+    }
+
+    if digits.is_empty() || digits.len() > 6 || characters.next() != Some('}') {
+        return None
+    }
+
+    u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+}
+
+/// Strips the indentation shared by all non-blank lines of a multi-line string, along with
+/// the line break after the opening `"""` and a whitespace-only line before the closing one.
+/// Tabs and spaces are not exchangeable, a line indented by a tab shares no indentation
+/// with a line indented by spaces.
+fn strip_indentation(body: &str) -> String {
+    let body = body.strip_prefix('\n').unwrap_or(body);
+    let mut lines: Vec<&str> = body.split('\n').collect();
+
+    if lines.len() > 1 && lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let indentation = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .reduce(|shared, indentation| {
+            let length = shared.bytes().zip(indentation.bytes()).take_while(|(left, right)| left == right).count();
+            &shared[..length]
+        })
+        .unwrap_or("");
+
+    lines.iter()
+        .map(|line| line.strip_prefix(indentation).unwrap_or_else(|| line.trim_start_matches([' ', '\t'])))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporter::ErrorFormat;
+
+    fn unicode_escape(source: &str) -> Option<char> {
+        scan_unicode_escape(&mut source.chars().peekable())
+    }
+
+    #[test]
+    fn unicode_escape_takes_one_to_six_digits() {
+        assert_eq!(unicode_escape("{41}"), Some('A'));
+        assert_eq!(unicode_escape("{1F600}"), Some('😀'));
+        assert_eq!(unicode_escape("{10FFFF}"), Some('\u{10FFFF}'));
+        assert_eq!(unicode_escape("{}"), None);
+        assert_eq!(unicode_escape("{0000041}"), None);
+    }
+
+    #[test]
+    fn unicode_escape_rejects_malformed_escapes() {
+        assert_eq!(unicode_escape("41"), None);
+        assert_eq!(unicode_escape("{41"), None);
+        assert_eq!(unicode_escape("{4G}"), None);
+        assert_eq!(unicode_escape("{D800}"), None);
+        assert_eq!(unicode_escape("{110000}"), None);
+    }
+
+    fn scan(source: &str) -> (Vec<TokenType>, usize) {
+        let reporter = CodeReporter::new(ErrorFormat::Muted);
+        let tokens = Scanner::new(reporter.clone()).scan_string(String::from("main.hunt"), source.to_string());
+
+        (tokens.into_iter().map(|token| token.token_type).collect(), reporter.error_count())
+    }
+
+    #[test]
+    fn escaped_line_break_breaks_a_single_line_string() {
+        let (token_types, errors) = scan("\"ab\\\nx");
+        assert!(token_types == [TokenType::INVALID, TokenType::LineBreak, TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(errors, 1);
+
+        assert_eq!(scan("\"ab\\\r\nx").1, 1);
+        assert_eq!(scan("r\"ab\\\nx").1, 1);
+    }
+
+    #[test]
+    fn escaped_line_break_is_reported_in_a_multi_line_string() {
+        let (token_types, errors) = scan("\"\"\"\nab\\\ncd\"\"\"");
+        assert!(token_types == [TokenType::STRING, TokenType::EOF]);
+        assert_eq!(errors, 1);
+    }
+
+    #[test]
+    fn strip_indentation_removes_shared_indentation() {
+        assert_eq!(strip_indentation("\n    a\n      b\n    "), "a\n  b");
+        assert_eq!(strip_indentation("\n\ta\n\n\t\tb\n"), "a\n\n\tb");
+        assert_eq!(strip_indentation("a\n  b"), "a\n  b");
+    }
+
+    #[test]
+    fn strip_indentation_keeps_mixed_tabs_and_spaces() {
+        assert_eq!(strip_indentation("\n\t  a\n    b\n"), "\t  a\n    b");
+        assert_eq!(strip_indentation("\n  \ta\n  b\n"), "\ta\nb");
+    }
+
+    #[test]
+    fn strip_indentation_empties_short_blank_lines() {
+        assert_eq!(strip_indentation("\n    a\n  \n    b\n"), "a\n\nb");
+    }
+}
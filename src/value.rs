@@ -0,0 +1,217 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use indexmap::IndexMap;
+use crate::environment::Environment;
+use crate::expressions::FunctionExpr;
+use crate::runtime::Definitions;
+
+/// Lists, maps and structs are shared by reference, assigning one to another binding or passing
+/// it to a function does not copy it, so changes through either name are visible to both.
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Empty,
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    List(Rc<RefCell<Vec<Value>>>),
+    /// Iterates in insertion order, so printing a map or looping over it is reproducible
+    Map(Rc<RefCell<IndexMap<MapKey, Value>>>),
+    Struct(Rc<RefCell<StructValue>>),
+    /// Enum values cannot be changed after construction, so they are shared without `RefCell`
+    Enum(Rc<EnumValue>),
+    Function(Rc<Closure>),
+    /// Module bound by `import name`
+    Module(Rc<ModuleValue>),
+}
+
+/// Instance of a user defined struct, the fields are kept in declaration order
+#[derive(PartialEq)]
+pub struct StructValue {
+    pub name: String,
+    pub fields: IndexMap<String, Value>,
+}
+
+#[derive(PartialEq)]
+pub struct EnumValue {
+    pub enum_name: String,
+    pub variant: String,
+    pub values: Vec<Value>,
+}
+
+/// Function together with the scope it was declared in, which stays visible to its body,
+/// and the functions and types of the module it was declared in
+pub struct Closure {
+    pub function: Rc<FunctionExpr>,
+    pub environment: Rc<RefCell<Environment>>,
+    pub definitions: Rc<RefCell<Definitions>>,
+}
+
+/// Functions are only equal to themselves
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// Top level functions and constants of a module
+#[derive(PartialEq)]
+pub struct ModuleValue {
+    pub name: String,
+    pub exports: IndexMap<String, Value>,
+}
+
+/// Values which can be used as key of a map. Floats are excluded, because `0.1 + 0.2`
+/// would not find the entry stored under `0.3`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Option<MapKey> {
+        match value {
+            Value::Str(value) => Some(MapKey::Str(value.to_string())),
+            Value::Int(value) => Some(MapKey::Int(*value)),
+            Value::Bool(value) => Some(MapKey::Bool(*value)),
+            _ => None
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Str(value) => Value::Str(value.to_string()),
+            MapKey::Int(value) => Value::Int(*value),
+            MapKey::Bool(value) => Value::Bool(*value),
+        }
+    }
+}
+
+impl Value {
+    /// Name of the type for error messages, struct instances report their struct name
+    pub fn type_name(&self) -> String {
+        let name = match self {
+            Value::Empty => "empty",
+            Value::Str(..) => "string",
+            Value::Int(..) => "int",
+            Value::Float(..) => "float",
+            Value::Bool(..) => "bool",
+            Value::List(..) => "list",
+            Value::Map(..) => "map",
+            Value::Struct(instance) => return instance.borrow().name.to_string(),
+            Value::Enum(value) => return value.enum_name.to_string(),
+            Value::Function(..) => "function",
+            Value::Module(..) => "module",
+        };
+
+        name.to_string()
+    }
+
+    pub fn list(elements: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn map(entries: IndexMap<MapKey, Value>) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    /// Printed form inside a list, map, struct or enum, where strings are quoted
+    /// so `["a, b"]` and `["a", "b"]` print differently
+    pub fn nested_string(&self) -> String {
+        self.format(true, &mut vec![])
+    }
+
+    /// `visiting` holds the lists, maps and structs printed around this value. One which
+    /// contains itself prints as `[...]`, `{...}` or `Point(...)` where it repeats.
+    fn format(&self, nested: bool, visiting: &mut Vec<*const ()>) -> String {
+        match self {
+            Value::Empty => String::new(),
+            Value::Str(value) if nested => format!("{:?}", value),
+            Value::Str(value) => value.to_string(),
+            Value::Int(value) => value.to_string(),
+            // floats always show a fractional part or exponent, so `3.0` stays distinguishable from `3`
+            Value::Float(value) => format!("{:?}", value),
+            Value::Bool(value) => value.to_string(),
+            Value::List(elements) => guarded(Rc::as_ptr(elements) as *const (), visiting, String::from("[...]"), |visiting| {
+                let elements: Vec<String> = elements.borrow().iter().map(|element| element.format(true, visiting)).collect();
+                format!("[{}]", elements.join(", "))
+            }),
+            Value::Map(entries) => guarded(Rc::as_ptr(entries) as *const (), visiting, String::from("{...}"), |visiting| {
+                let entries: Vec<String> = entries.borrow().iter()
+                    .map(|(key, value)| format!("{}: {}", key.to_value().nested_string(), value.format(true, visiting)))
+                    .collect();
+
+                format!("{{{}}}", entries.join(", "))
+            }),
+            Value::Struct(instance) => {
+                let repeated = format!("{}(...)", instance.borrow().name);
+                guarded(Rc::as_ptr(instance) as *const (), visiting, repeated, |visiting| {
+                    let instance = instance.borrow();
+                    let fields: Vec<String> = instance.fields.iter()
+                        .map(|(name, value)| format!("{}={}", name, value.format(true, visiting)))
+                        .collect();
+
+                    format!("{}({})", instance.name, fields.join(", "))
+                })
+            },
+            Value::Enum(value) if value.values.is_empty() => format!("{}.{}", value.enum_name, value.variant),
+            Value::Enum(value) => {
+                let values: Vec<String> = value.values.iter().map(|value| value.format(true, visiting)).collect();
+                format!("{}.{}({})", value.enum_name, value.variant, values.join(", "))
+            },
+            Value::Function(closure) => format!("<fun {}>", closure.function.qualified_name()),
+            Value::Module(module) => format!("<module {}>", module.name),
+        }
+    }
+
+    /// Truthiness of a value wherever a condition is expected: `if`, `while`,
+    /// `and`, `or`, `not` and `!`.
+    ///
+    /// | type     | falsy   | truthy          |
+    /// |----------|---------|-----------------|
+    /// | bool     | `false` | `true`          |
+    /// | int      | `0`     | any other int   |
+    /// | float    | `0.0`   | any other float |
+    /// | string   | `""`    | any other string|
+    /// | list     | `[]`    | any other list  |
+    /// | map      | `{}`    | any other map   |
+    /// | struct   | never   | always          |
+    /// | enum     | never   | always          |
+    /// | function | never   | always          |
+    /// | module   | never   | always          |
+    /// | empty    | always  | never           |
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Empty => false,
+            Value::Str(value) => !value.is_empty(),
+            Value::Int(value) => *value != 0,
+            Value::Float(value) => *value != 0.0,
+            Value::Bool(value) => *value,
+            Value::List(elements) => !elements.borrow().is_empty(),
+            Value::Map(entries) => !entries.borrow().is_empty(),
+            Value::Struct(..) | Value::Enum(..) | Value::Function(..) | Value::Module(..) => true,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format(false, &mut vec![]))
+    }
+}
+
+/// Formats a shared value unless it is already being formatted further out
+fn guarded(address: *const (), visiting: &mut Vec<*const ()>, repeated: String, format: impl FnOnce(&mut Vec<*const ()>) -> String) -> String {
+    if visiting.contains(&address) {
+        return repeated;
+    }
+
+    visiting.push(address);
+    let output = format(visiting);
+    visiting.pop();
+
+    output
+}